@@ -1,69 +1,203 @@
+use std::collections::HashSet;
 use std::env;
 use std::ffi::OsStr;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
-use std::path::PathBuf;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-use anyhow::{anyhow, Context, Ok, Result};
+use anyhow::{anyhow, Context, Result};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::config::{self, Config};
+use crate::download::{self, Download};
+use crate::extract::{self, Limits};
+use crate::git;
+use crate::index::Index;
+use crate::meta::{ArchiveMeta, CacheMeta};
 use crate::page::{Kind, Page};
 use crate::platform::Platform;
+use crate::store;
 
 
-const OFFICIAL_PAGES_ARCHIVE_URL: &str = "https://tldr.sh/assets/tldr.zip";
-const PAGES_DIR: &str = "tldr-pages";
+pub(crate) const PAGES_DIR: &str = "tldr-pages";
 
 
+/// A page file laid out as `<root>/<lang>/<platform>/<name>.md`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PageFile {
+    pub lang: String,
+    pub platform: Platform,
+    pub name: String,
+}
+
+impl PageFile {
+    pub fn path(&self, root: &Path) -> PathBuf {
+        root.join(&self.lang).join(self.platform.to_string()).join(format!("{}.md", self.name))
+    }
+}
+
+/// Dir of the current version of the official pages.
+pub(crate) fn official_pages_dir(config: &Config) -> Result<PathBuf> {
+    Ok(store::pages_dir(&config.get_official_page_dir()?))
+}
+
+/// Official and private pages dirs with the kind of pages in it, official comes first then
+/// private ones by priority.
+pub(crate) fn get_sources(config: &Config) -> Result<Vec<(PathBuf, Kind)>> {
+    let mut sources = vec![(official_pages_dir(config)?, Kind::Official)];
+    for d in &config.private_pages_dirs {
+        sources.push((d.path.to_owned(), Kind::Private(d.name.to_owned())));
+    }
+    Ok(sources)
+}
+
+/// Find every page file under `root` for all languages and platforms.
+pub(crate) fn scan(root: &Path) -> Vec<PageFile> {
+    let read_dir = |dir: &Path| -> Vec<(String, PathBuf)> {
+        let mut entries = fs::read_dir(dir)
+            .map(|entries| entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok().map(|n| (n, e.path())))
+                .collect())
+            .unwrap_or_else(|_| Vec::new());
+        entries.sort();
+        entries
+    };
+
+    let mut files = Vec::new();
+    let lang_dirs = read_dir(root).into_iter()
+        .filter(|(lang, dir)| (lang == "pages" || lang.starts_with("pages.")) && dir.is_dir());
+    for (lang, lang_dir) in lang_dirs {
+        for (platform, platform_dir) in read_dir(&lang_dir).into_iter().filter(|(_, d)| d.is_dir()) {
+            for (filename, _) in read_dir(&platform_dir) {
+                if let Some(name) = filename.strip_suffix(".md") {
+                    files.push(PageFile {
+                        lang: lang.to_owned(),
+                        platform: platform.parse().unwrap_or_default(),
+                        name: name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    files
+}
+
 pub(crate) fn seek<'a>(command: &'a str, config: &'a Config) -> Result<Vec<Page<'a>>> {
-    let pages_dir = match config.official_pages_dir {
-        Some(ref d) => d.to_owned(),
-        None => config::get_default_pages_dir()?,
-    }.join(PAGES_DIR);
+    let lang_folders = get_lang_folders(config);
+    let platform = config.platform.as_ref().unwrap_or(&Platform::Common);
 
+    if let Some(index) = Index::load(config) {
+        return Ok(seek_index(&index, command, &lang_folders, platform, config));
+    }
+
+    let filename = &format!("{}.md", command);
+    let mut pages = Vec::with_capacity(2);
+    for (dir, kind) in get_sources(config)? {
+        if let Some(page) = do_seek(dir, &lang_folders, platform, filename, kind, config) {
+            pages.push(page)
+        }
+    }
+    Ok(pages)
+}
+
+fn seek_index<'a>(
+    index: &Index,
+    command: &str,
+    lang_folders: &[String],
+    platform: &Platform,
+    config: &'a Config,
+) -> Vec<Page<'a>> {
+    let mut platforms = vec![platform];
+    if *platform != Platform::Common {
+        platforms.push(&Platform::Common);
+    }
+    let candidates = index.entries.iter().filter(|e| e.file.name == command).collect::<Vec<_>>();
+
+    let mut pages = Vec::with_capacity(2);
+    for (i, source) in index.sources.iter().enumerate() {
+        let found = lang_folders.iter()
+            .flat_map(|lang| platforms.iter().map(move |p| (lang, p)))
+            .find_map(|(lang, p)| candidates.iter().find(|e| {
+                e.source == i && e.file.lang == *lang && e.file.platform == **p
+            }));
+        if let Some(entry) = found {
+            let file = index.page_path(entry);
+            if let Some(page) = Page::option_from(file, source.kind.clone(), entry.file.platform.clone(), config) {
+                pages.push(page);
+            }
+        }
+    }
+    pages
+}
+
+/// Language folders in the order of preference, english `pages` always comes last.
+fn get_lang_folders(config: &Config) -> Vec<String> {
     let mut lang_folders = Vec::with_capacity(config.languages.len() + 1);
     for lang in &config.languages {
         if lang == "en" {
             break;
         }
-        lang_folders.push(format!("pages.{}", lang));
+        lang_folders.push(lang_folder(lang));
     }
-    lang_folders.push("pages".to_string());
+    lang_folders.push(lang_folder("en"));
+    lang_folders
+}
 
-    let platform = config.platform.as_ref().unwrap_or(&Platform::Common);
+/// `pages.<lang>`, or `pages` for english.
+pub(crate) fn lang_folder(lang: &str) -> String {
+    match lang {
+        "en" => "pages".to_string(),
+        _ => format!("pages.{}", lang),
+    }
+}
 
-    let filename = &format!("{}.md", command);
+/// Collect all official and private pages for the preferred languages, filtered by the
+/// configured platform. A page shows up once per source and platform, in the language
+/// that fits best.
+pub(crate) fn walk(config: &Config) -> Result<Vec<Page<'_>>> {
+    let sources = get_sources(config)?;
+    let (roots, files) = page_files(config, &sources);
+    let lang_folders = get_lang_folders(config);
+    let filter_platform = |platform: &Platform| match config.platform {
+        Some(ref p) => *platform == Platform::Common || platform == p,
+        None => true,
+    };
 
-    let mut pages = Vec::with_capacity(2);
-    if let Some(page) = do_seek(
-        pages_dir,
-        &lang_folders,
-        platform,
-        filename,
-        Kind::Official,
-        config,
-    ) {
-        pages.push(page)
-    }
-
-    if let Some(ref d) = config.private_pages_dir {
-        if let Some(page) = do_seek(
-            d.to_owned(),
-            &lang_folders,
-            platform,
-            filename,
-            Kind::Private,
-            config,
-        ) {
-            pages.push(page)
+    let mut pages = Vec::new();
+    let mut seen = HashSet::new();
+    for lang in &lang_folders {
+        for (i, file) in files.iter().filter(|(_, f)| f.lang == *lang && filter_platform(&f.platform)) {
+            if !seen.insert((i, &file.platform, &file.name)) {
+                continue;
+            }
+            let kind = sources[*i].1.clone();
+            if let Some(page) = Page::option_from(file.path(&roots[*i]), kind, file.platform.clone(), config) {
+                pages.push(page);
+            }
         }
     }
-
     Ok(pages)
 }
 
+/// Roots of the sources and every page file in them with the position of its source, from
+/// the index if it is fresh.
+pub(crate) fn page_files(config: &Config, sources: &[(PathBuf, Kind)]) -> (Vec<PathBuf>, Vec<(usize, PageFile)>) {
+    match Index::load(config) {
+        Some(index) => (
+            index.sources.into_iter().map(|s| s.root).collect(),
+            index.entries.into_iter().map(|e| (e.source, e.file)).collect(),
+        ),
+        None => (
+            sources.iter().map(|(root, _)| root.to_owned()).collect(),
+            sources.iter().enumerate()
+                .flat_map(|(i, (root, _))| scan(root).into_iter().map(move |f| (i, f)))
+                .collect(),
+        ),
+    }
+}
+
 fn do_seek<'a, T>(
     pages_dir: PathBuf,
     lang_folders: &[T],
@@ -78,12 +212,12 @@ where
     let lang_dirs = lang_folders.iter().map(|f| pages_dir.join(f));
     for dir in lang_dirs {
         let file = dir.join(platform.to_string()).join(filename);
-        if let Some(page) = Page::option_from(file, kind, platform.clone(), config) {
+        if let Some(page) = Page::option_from(file, kind.clone(), platform.clone(), config) {
             return Some(page);
         }
         if *platform != Platform::Common {
             let file = dir.join(Platform::Common.to_string()).join(filename);
-            if let Some(page) = Page::option_from(file, kind, Platform::Common, config) {
+            if let Some(page) = Page::option_from(file, kind.clone(), Platform::Common, config) {
                 return Some(page);
             }
         }
@@ -92,56 +226,162 @@ where
 }
 
 pub(crate) fn update(config: &Config) -> Result<()> {
+    let lock = store::lock(&config.get_official_page_dir()?)?;
+    let official = update_official(config, &lock);
+    let private = sync_private(config);
+    let index = Index::rebuild(config).context("Fail to build pages index");
+
+    let mut errors = [official, private, index].into_iter()
+        .filter_map(Result::err)
+        .collect::<Vec<_>>();
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(anyhow!(errors.iter().map(|e| format!("{:?}", e)).collect::<Vec<_>>().join("\n\n"))),
+    }
+}
+
+/// Pull every private pages dir which is a git checkout tracking an upstream.
+fn sync_private(config: &Config) -> Result<()> {
+    let mut failures = Vec::new();
+    let dirs = config.private_pages_dirs.iter()
+        .filter(|d| git::is_repo(&d.path) && git::has_upstream(&d.path));
+    for d in dirs {
+        match git::pull(&d.path) {
+            Ok(()) => eprintln!("Synced private pages: {}", d.name),
+            Err(e) => failures.push(format!("{}: {:#}", d.name, e)),
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Fail to sync private pages:\n{}", failures.join("\n")))
+    }
+}
+
+fn update_official(config: &Config, lock: &store::Lock) -> Result<()> {
     let dir = config.get_official_page_dir()?;
     fs::create_dir_all(&dir)
         .with_context(|| format!("Fail to create directory: {}", &dir.display()))?;
 
-    let filename = download_archive(config)?;
-    let file = File::open(&filename)
-        .with_context(|| format!("Could not open file: {}", &filename.display()))?;
-    let mut archive = zip::ZipArchive::new(BufReader::new(file))
-        .with_context(|| format!("Error preparing to unzip: {}", &filename.display()))?;
-
-    let tmp_dir = dir.join("tmp-pages");
-    let pages_dir = dir.join(PAGES_DIR);
-
-    archive
-        .extract(&tmp_dir)
-        .with_context(|| format!("Fail to extract archive to: {}", &tmp_dir.display()))?;
-    if pages_dir.is_dir() {
-        fs::remove_dir_all(&pages_dir)
-            .with_context(|| format!("Fail to clean up: {}", &pages_dir.display()))?;
-    }
-    fs::rename(&tmp_dir, &pages_dir).with_context(|| {
-        format!(
-            "Error swapping dir: {} -> {}",
-            &tmp_dir.display(),
-            &pages_dir.display()
-        )
-    })?;
-    fs::remove_file(&filename)
-        .with_context(|| format!("Fail to clean up archive: {}", &filename.display()))?;
-    Ok(())
+    let current = official_pages_dir(config)?;
+    let cached = CacheMeta::load(&current).ok().flatten();
+    let languages = match config.get_languages_to_cache() {
+        Some(langs) => {
+            if config.archive_sha256.is_some() {
+                eprintln!("`archive_sha256` is of the archive of all languages, not checked with `languages_to_cache`");
+            }
+            langs.into_iter().map(Some).collect()
+        }
+        None => vec![None],
+    };
+
+    // `None` for the ones to keep from the current cache
+    let mut downloads: Vec<(Option<&str>, Option<Download>)> = Vec::with_capacity(languages.len());
+    let mut failures = Vec::new();
+    for lang in languages.iter().map(Option::as_deref) {
+        let cached = cached.as_ref().and_then(|m| m.archive(lang));
+        match (download::download_archive(config, lang, cached), lang) {
+            (Ok(d), _) => downloads.push((lang, d)),
+            (Err(e), Some(_)) if e.is::<download::NotPublished>() => {}
+            // english pages are the fallback of all others, can't go without
+            (Err(e), Some(l)) if l != "en" => {
+                eprintln!("Keep the cached pages of language {}, if any", l);
+                failures.push(e.context(format!("Fail to update pages of language {}", l)));
+                downloads.push((lang, None));
+            }
+            (Err(e), _) => {
+                for d in downloads.iter().filter_map(|(_, d)| d.as_ref()) {
+                    let _ = fs::remove_file(&d.archive);
+                }
+                return Err(e);
+            }
+        }
+    }
+    if downloads.iter().all(|(_, d)| d.is_none()) {
+        if let Some(mut meta) = cached.filter(|_| failures.is_empty()) {
+            meta.touch();
+            meta.save(&current)?;
+        }
+        return merge_failures(failures);
+    }
+
+    let (version, pages_dir) = store::new_version(&dir, lock)?;
+    let assembled = assemble(&pages_dir, &current, cached.as_ref(), &downloads);
+    for d in downloads.iter().filter_map(|(_, d)| d.as_ref()) {
+        let _ = fs::remove_file(&d.archive);
+    }
+    // a half done version is removed by the next update
+    let archives = assembled?;
+    CacheMeta::new(archives, scan(&pages_dir).len()).save(&pages_dir)?;
+    let changes = store::diff(&current, &pages_dir);
+    store::activate(&dir, &version, lock)?;
+    eprint!("{}", changes);
+    merge_failures(failures)
 }
 
-fn download_archive(config: &Config) -> Result<PathBuf> {
-    let url = OFFICIAL_PAGES_ARCHIVE_URL;
-    let mut resp = reqwest::blocking::get(url)?
-        .error_for_status()
-        .with_context(|| format!("Fail to download archive form: {}", url))?;
-    let dir = config.get_official_page_dir()?;
+/// Fill `pages_dir` with the pages of each language, from its download or else the `current`
+/// cache. Returns the archives the pages come from.
+fn assemble(
+    pages_dir: &Path,
+    current: &Path,
+    cached: Option<&CacheMeta>,
+    downloads: &[(Option<&str>, Option<Download>)],
+) -> Result<Vec<ArchiveMeta>> {
+    let mut archives = Vec::with_capacity(downloads.len());
+    for (lang, download) in downloads {
+        let lang_dir = lang.map(lang_folder).unwrap_or_default();
+        match download {
+            Some(d) => {
+                extract_archive(&d.archive, &pages_dir.join(&lang_dir))?;
+                archives.push(d.meta.clone());
+            }
+            None => {
+                store::copy_dir(&current.join(&lang_dir), &pages_dir.join(&lang_dir))?;
+                archives.extend(cached.and_then(|m| m.archive(*lang)).cloned());
+            }
+        }
+    }
+    Ok(archives)
+}
 
-    let archive = dir.join("tldr.zip");
+/// The pages of the failed languages were kept from the cache, still the update is not done.
+fn merge_failures(mut failures: Vec<anyhow::Error>) -> Result<()> {
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0)),
+        _ => Err(anyhow!(failures.iter().map(|e| format!("{:#}", e)).collect::<Vec<_>>().join("\n"))),
+    }
+}
 
-    let mut file = File::create(&archive)
-        .with_context(|| format!("Fail to create archive: {}", archive.display()))?;
-    let mut buf = BufWriter::new(&mut file);
-    resp.copy_to(&mut buf).with_context(|| format!("Fail to copy archive stream"))?;
-    Ok(archive)
+fn extract_archive(filename: &Path, dir: &Path) -> Result<()> {
+    let file = File::open(filename)
+        .with_context(|| format!("Could not open file: {}", filename.display()))?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("Error preparing to unzip: {}", filename.display()))?;
+    extract::extract(&mut archive, dir, Limits::default())
+        .with_context(|| format!("Fail to extract archive to: {}", dir.display()))
+}
+
+/// Switch the official pages back to the ones before the last update.
+pub(crate) fn rollback(config: &Config) -> Result<()> {
+    let dir = config.get_official_page_dir()?;
+    let lock = store::lock(&dir)?;
+    store::rollback(&dir, &lock)?;
+    let pages_dir = official_pages_dir(config)?;
+    match CacheMeta::load(&pages_dir)? {
+        Some(meta) => {
+            let urls = meta.archives.iter().map(|a| a.source_url.as_str()).collect::<Vec<_>>();
+            eprintln!("Rolled back pages cache to the one from: {}", urls.join(", "));
+        }
+        None => eprintln!("Rolled back pages cache to: {}", pages_dir.display()),
+    }
+    Index::rebuild(config).context("Fail to build pages index")
 }
 
 pub(crate) fn edit<'a>(command: &'a str, args: &'a config::Args, config: &'a Config) -> Result<()> {
-    let dir = config.private_pages_dir.as_deref().ok_or(anyhow!("Private pages dir not configured"))?;
+    let source = config.get_private_pages_dir(args.source.as_deref())?;
+    let dir = &source.path;
     let platform = args.platform.as_ref().unwrap_or(&Platform::Common);
     let filename = &format!("{}.md", command);
     let file = dir.join("pages").join(platform.to_string()).join(filename);
@@ -167,11 +407,35 @@ pub(crate) fn edit<'a>(command: &'a str, args: &'a config::Args, config: &'a Con
         .stdout(Stdio::inherit())
         .stdout(Stdio::inherit())
         .output()?;
+
+    if let Err(e) = Index::rebuild(config) {
+        eprintln!("Fail to rebuild pages index: {:#}", e);
+    }
+    if source.git_push && file.is_file() && git::is_repo(dir) {
+        let message = format!("Update page: {}/{}", platform, command);
+        git::commit_and_push(dir, &file, &message)
+            .with_context(|| format!("Fail to push private pages: {}", source.name))?;
+    }
     Ok(())
 }
 
 
+#[allow(clippy::needless_return, clippy::useless_conversion)]
 pub(crate) fn list(config: &Config) -> Result<()> {
+    if let Some(index) = Index::load(config) {
+        let mut pages = index.entries.iter()
+            .filter(|e| match config.platform {
+                Some(ref p) => e.file.platform == Platform::Common || e.file.platform == *p,
+                None => true,
+            })
+            .map(|e| e.file.name.as_str())
+            .collect::<Vec<&str>>();
+        pages.sort_unstable();
+        pages.dedup();
+        println!("{}", pages.join("\n"));
+        return Ok(());
+    }
+
     let filter_platform: Box<dyn Fn(&DirEntry) -> bool> = match config.platform.as_ref() {
         Some(platform) => {
             Box::new(|entry: &DirEntry| -> bool {
@@ -196,10 +460,7 @@ pub(crate) fn list(config: &Config) -> Result<()> {
         }
     };
 
-    let pages_dir = match config.official_pages_dir {
-        Some(ref d) => d.to_owned(),
-        None => config::get_default_pages_dir()?,
-    }.join(PAGES_DIR);
+    let pages_dir = official_pages_dir(config)?;
 
     let mut pages = WalkDir::new(pages_dir)
         .min_depth(2)
@@ -209,8 +470,8 @@ pub(crate) fn list(config: &Config) -> Result<()> {
         .filter_map(filter_pages)
         .collect::<Vec<String>>();
 
-    if let Some(ref dir) = config.private_pages_dir {
-        let ps = WalkDir::new(dir)
+    for dir in &config.private_pages_dirs {
+        let ps = WalkDir::new(&dir.path)
             .min_depth(2)
             .into_iter()
             .filter_entry(&filter_platform)
@@ -226,3 +487,136 @@ pub(crate) fn list(config: &Config) -> Result<()> {
 
     Ok(())
 }
+
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::{Cursor, Write};
+    use std::sync::{Arc, Mutex};
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+    use crate::download::test::{listen, response};
+
+    type Files = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Serve `files` by path with the `ETag` of their length, 503 for the empty ones and 404
+    /// for the missing ones. Returns the server address.
+    fn serve(files: Files) -> String {
+        listen(move |head| {
+            let path = head.first().and_then(|l| l.split(' ').nth(1)).unwrap_or_default();
+            match files.lock().unwrap().get(path.trim_start_matches('/')) {
+                None => response("404 Not Found", "", b""),
+                Some(body) if body.is_empty() => response("503 Service Unavailable", "", b""),
+                Some(body) => {
+                    let etag = format!("\"{}\"", body.len());
+                    if head.iter().any(|l| l.eq_ignore_ascii_case(&format!("if-none-match: {}", etag))) {
+                        b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_vec()
+                    } else {
+                        response("200 OK", &format!("ETag: {}\r\n", etag), body)
+                    }
+                }
+            }
+        })
+    }
+
+    fn archives_left(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir).unwrap()
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .filter(|n| n.ends_with(".zip"))
+            .collect()
+    }
+
+    #[test]
+    fn update_languages_to_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let files: Files = Arc::default();
+        let set = |name: &str, content: Vec<u8>| { files.lock().unwrap().insert(name.to_string(), content); };
+        set("tldr-pages.en.zip", zip(&[("common/tar.md", "# tar\n\n> Archiving utility.\n")]));
+        set("tldr-pages.zh.zip", zip(&[("common/tar.md", "# tar\n\n> 归档工具。\n")]));
+        set("tldr-pages.ja.zip", Vec::new());
+        let addr = serve(Arc::clone(&files));
+        let config = Config {
+            official_pages_dir: Some(tmp.path().to_owned()),
+            archive_url: Some(format!("http://{}/tldr.zip", addr)),
+            // `de_DE` is not published, so quietly skipped
+            languages_to_cache: Some(vec!["zh".into(), "de_DE".into(), "ja".into()]),
+            download_retries: Some(0),
+            ..Default::default()
+        };
+        let page = |lang: &str| official_pages_dir(&config).unwrap().join(lang).join("common/tar.md");
+
+        // a failed language is reported, still the others get updated
+        let e = format!("{:#}", update(&config).unwrap_err());
+        assert!(e.contains("language ja") && !e.contains("de_DE"), "{}", e);
+        assert!(page("pages").is_file() && page("pages.zh").is_file());
+        assert!(!page("pages.ja").exists() && !page("pages.de_DE").exists());
+        assert!(archives_left(tmp.path()).is_empty());
+
+        set("tldr-pages.ja.zip", zip(&[("common/tar.md", "# tar\n\n> アーカイブ。\n")]));
+        let before = page("pages");
+        update(&config).unwrap();
+        assert!(page("pages.ja").is_file());
+        // the not modified languages are kept from the previous version
+        assert_ne!(page("pages"), before);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(fs::metadata(page("pages")).unwrap().ino(), fs::metadata(&before).unwrap().ino());
+        }
+        let meta = CacheMeta::load(&official_pages_dir(&config).unwrap()).unwrap().unwrap();
+        assert_eq!(meta.archives.len(), 3);
+
+        // nothing modified is an update too
+        update(&config).unwrap();
+
+        // a broken archive fails the update, the downloaded ones are cleaned up
+        let current = official_pages_dir(&config).unwrap();
+        set("tldr-pages.en.zip", zip(&[("common/tar.md", "# tar\n\n> Archive utility.\n")]));
+        set("tldr-pages.zh.zip", b"broken".to_vec());
+        set("tldr-pages.ja.zip", zip(&[("common/tar.md", "# tar\n\n> アーカイブ用。\n")]));
+        assert!(update(&config).is_err());
+        assert_eq!(official_pages_dir(&config).unwrap(), current);
+        assert!(archives_left(tmp.path()).is_empty());
+
+        // english can't be kept from the cache
+        set("tldr-pages.zh.zip", zip(&[("common/tar.md", "# tar\n\n> 归档。\n")]));
+        set("tldr-pages.en.zip", Vec::new());
+        assert!(update(&config).is_err());
+        assert_eq!(official_pages_dir(&config).unwrap(), current);
+        assert_eq!(fs::read_to_string(page("pages.zh")).unwrap(), "# tar\n\n> 归档工具。\n");
+        assert!(archives_left(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn update_languages_to_cache_from_file_mirror() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mirror = tmp.path().join("mirror");
+        fs::create_dir_all(&mirror).unwrap();
+        fs::write(mirror.join("tldr-pages.en.zip"), zip(&[("common/tar.md", "# tar\n\n> Archiving utility.\n")])).unwrap();
+        fs::write(mirror.join("tldr-pages.zh.zip"), zip(&[("common/tar.md", "# tar\n\n> 归档工具。\n")])).unwrap();
+        let config = Config {
+            official_pages_dir: Some(tmp.path().join("cache")),
+            archive_url: Some(reqwest::Url::from_file_path(mirror.join("tldr.zip")).unwrap().to_string()),
+            // the mirror has no `ja` archive, as if it was not published
+            languages_to_cache: Some(vec!["zh".into(), "ja".into()]),
+            ..Default::default()
+        };
+        update(&config).unwrap();
+        let pages_dir = official_pages_dir(&config).unwrap();
+        assert!(pages_dir.join("pages.zh/common/tar.md").is_file());
+        assert!(!pages_dir.join("pages.ja").exists());
+    }
+}
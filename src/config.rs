@@ -1,15 +1,25 @@
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{env, fs};
 
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use serde::Deserialize;
 
 use crate::platform::Platform;
+use crate::style::{ColorLevel, RawStyle, Style};
 
 
+const OFFICIAL_PAGES_ARCHIVE_URL: &str = "https://tldr.sh/assets/tldr.zip";
+const DEFAULT_PRIVATE_SOURCE: &str = "private";
+const DEFAULT_CACHE_MAX_AGE_DAYS: u64 = 30;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_DOWNLOAD_RETRIES: u32 = 3;
+
 #[derive(Debug, Parser)]
 #[clap(arg_required_else_help = true)]
+#[clap(group(ArgGroup::new("sourced").args(&["edit", "export-html"])))]
 #[clap(author, version, about)]
 pub(crate) struct Args {
     /// Show pages for this command. <git | git merge | ...>
@@ -20,14 +30,26 @@ pub(crate) struct Args {
     #[clap(short, long)]
     pub platform: Option<Platform>,
 
-    /// Updates the offline cache of pages
+    /// Updates the offline cache of pages and syncs git backed private pages
     #[clap(short, long)]
     pub update: bool,
 
+    /// Restore the official pages cache from before the last update.
+    #[clap(long)]
+    pub rollback: bool,
+
+    /// Show when the pages cache was updated, where from and how many pages it has.
+    #[clap(long)]
+    pub cache_info: bool,
+
     /// Lists all pages or with `-p` option to filter by platform.
     #[clap(short, long)]
     pub list: bool,
 
+    /// Search pages whose descriptions or examples match all words of the query.
+    #[clap(long, value_name = "QUERY")]
+    pub search: Option<String>,
+
     /// Show pages for the given language. Option: [zh | zh_TW | ...]
     #[clap(short = 'L', long)]
     pub language: Option<String>,
@@ -36,13 +58,54 @@ pub(crate) struct Args {
     #[clap(long)]
     pub styled: Option<StyledChoice>,
 
+    /// Output format of pages. Choice: [text | json | man]
+    #[clap(long)]
+    pub format: Option<Format>,
+
+    /// Render a markdown file, or stdin with `-`, as a page to preview it.
+    #[clap(long, value_name = "FILE")]
+    pub render: Option<PathBuf>,
+
+    /// Print the markdown of pages verbatim.
+    #[clap(long, conflicts_with_all = &["format", "which"])]
+    pub raw: bool,
+
+    /// Print the kind, platform and file path of every matched page.
+    #[clap(long, conflicts_with = "format")]
+    pub which: bool,
+
+    /// Wrap pages to this many columns instead of the terminal width, 0 to disable wrapping.
+    #[clap(long, value_name = "COLUMNS")]
+    pub width: Option<usize>,
+
+    /// Print pages straight to stdout instead of through the pager.
+    #[clap(long)]
+    pub no_pager: bool,
+
     /// Print version.
     #[clap(short = 'v', long)]
     pub version: bool,
 
     /// Edit command.
     #[clap(short, long)]
-    pub edit: bool
+    pub edit: bool,
+
+    /// Pick an example, fill its placeholders, then print, copy or run it.
+    #[clap(short, long)]
+    pub interactive: bool,
+
+    /// Lint pages of the command, a page file or dir. All private pages if none given.
+    #[clap(long)]
+    pub lint: bool,
+
+    /// Export pages of all languages and platforms to a static HTML site in this dir.
+    #[clap(long, value_name = "OUTDIR")]
+    pub export_html: Option<PathBuf>,
+
+    /// Edit pages in the private pages dir with this name, or export pages of this source
+    /// only, `official` for the official pages.
+    #[clap(long, requires = "sourced")]
+    pub source: Option<String>,
 }
 
 
@@ -67,6 +130,7 @@ impl std::str::FromStr for StyledChoice {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for StyledChoice {
     fn default() -> Self {
         Self::Auto
@@ -74,14 +138,78 @@ impl Default for StyledChoice {
 }
 
 
+/// How to refresh the official pages cache once it is older than the max age.
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AutoUpdate {
+    /// Only warn about it.
+    #[default]
+    Off,
+    /// Update before showing pages.
+    Foreground,
+    /// Update in a background process, pages are shown from the stale cache meanwhile.
+    Background,
+}
+
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub(crate) enum Format {
+    #[default]
+    Text,
+    Json,
+    /// Roff for `man -l -`.
+    Man,
+}
+
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "man" => Ok(Format::Man),
+            _ => Err(anyhow!("Unknown format: {}. Choice: [text | json | man]", s))
+        }
+    }
+}
+
+
 #[derive(Debug, Default)]
 pub(crate) struct Config {
     pub official_pages_dir: Option<PathBuf>,
-    pub private_pages_dir: Option<PathBuf>,
+    /// Sorted by priority, the highest comes first.
+    pub private_pages_dirs: Vec<PrivatePagesDir>,
     pub platform: Option<Platform>,
     pub languages: Vec<String>,
     pub styled: StyledChoice,
+    pub style: Style,
+    pub format: Format,
+    /// Overrides the terminal width.
+    pub width: Option<usize>,
     pub editor: Option<String>,
+    /// Overrides `$PAGER`, empty to disable paging.
+    pub pager: Option<String>,
+    pub no_pager: bool,
+    pub archive_url: Option<String>,
+    pub archive_mirrors: Vec<String>,
+    /// Pinned SHA-256 of the archive, overrides the published one.
+    pub archive_sha256: Option<String>,
+    /// Refuse archives without a pinned or published SHA-256.
+    pub require_checksum: bool,
+    /// Minisign public key to verify the archive signature with.
+    pub archive_public_key: Option<String>,
+    /// Cache these languages only besides english, `auto` for the ones of the environment.
+    pub languages_to_cache: Option<Vec<String>>,
+    /// Longest wait for the connection to the server to be set up.
+    pub connect_timeout_secs: Option<u64>,
+    /// Longest wait for the server to respond or send more data.
+    pub read_timeout_secs: Option<u64>,
+    /// Retries of downloads failed by network errors or server errors.
+    pub download_retries: Option<u32>,
+    /// In days, 0 to never consider the cache stale.
+    pub cache_max_age_days: Option<u64>,
+    pub auto_update: AutoUpdate,
 }
 
 impl Config {
@@ -110,9 +238,19 @@ impl Config {
         if let Some(styled) = args.styled {
             self.styled = styled
         }
+        if let Some(format) = args.format {
+            self.format = format
+        }
+        if args.width.is_some() {
+            self.width = args.width;
+        }
+        if args.no_pager {
+            self.no_pager = true;
+        }
         if args.platform.is_some() {
             self.platform = args.platform.clone();
         }
+        self.style = std::mem::take(&mut self.style).degrade(ColorLevel::detect());
         if let Some(lang) = &args.language {
             self.languages = vec![lang.to_string()];
         } else {
@@ -126,8 +264,80 @@ impl Config {
             None => Ok(get_default_pages_dir()?),
         }
     }
+
+    /// The private pages dir named `source`, or the one with highest priority if `None`.
+    pub fn get_private_pages_dir(&self, source: Option<&str>) -> Result<&PrivatePagesDir> {
+        match source {
+            Some(name) => self.private_pages_dirs.iter().find(|d| d.name == name)
+                .ok_or_else(|| anyhow!("Private pages dir not configured: {}", name)),
+            None => self.private_pages_dirs.first()
+                .ok_or_else(|| anyhow!("Private pages dir not configured")),
+        }
+    }
+
+    /// Age after which the official pages cache is stale, `None` if it never is.
+    pub fn get_cache_max_age(&self) -> Option<Duration> {
+        match self.cache_max_age_days.unwrap_or(DEFAULT_CACHE_MAX_AGE_DAYS) {
+            0 => None,
+            days => Some(Duration::from_secs(days * 24 * 60 * 60)),
+        }
+    }
+
+    pub fn get_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS))
+    }
+
+    pub fn get_read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs.unwrap_or(DEFAULT_READ_TIMEOUT_SECS))
+    }
+
+    pub fn get_download_retries(&self) -> u32 {
+        self.download_retries.unwrap_or(DEFAULT_DOWNLOAD_RETRIES)
+    }
+
+    /// Archive download links in the order they should be tried.
+    pub fn get_archive_urls(&self) -> Vec<&str> {
+        let mut urls = Vec::with_capacity(self.archive_mirrors.len() + 1);
+        urls.push(self.archive_url.as_deref().unwrap_or(OFFICIAL_PAGES_ARCHIVE_URL));
+        urls.extend(self.archive_mirrors.iter().map(String::as_str));
+        urls
+    }
+
+    /// Links of the `tldr-pages.<lang>.zip` archives next to the archive download links.
+    pub fn get_language_archive_urls(&self, language: &str) -> Vec<String> {
+        self.get_archive_urls()
+            .into_iter()
+            .map(|url| {
+                let dir = url.rsplit_once('/').map_or("", |(dir, _)| dir);
+                format!("{}/tldr-pages.{}.zip", dir, language)
+            })
+            .collect()
+    }
+
+    /// English and the configured languages to cache, `None` to cache all languages.
+    ///
+    /// `auto` expands to the languages of the environment. Region forms of them, like
+    /// `zh_TW`, are kept for the few tldr-pages has, the update skips the others. English
+    /// ones never are, the English pages are always cached.
+    pub fn get_languages_to_cache(&self) -> Option<Vec<String>> {
+        let configured = self.languages_to_cache.as_ref()?;
+        let mut languages = vec!["en".to_string()];
+        for lang in configured {
+            let expanded = match lang.as_str() {
+                "auto" => get_env_languages().into_iter().filter(|l| !l.starts_with("en_")).collect(),
+                _ => vec![lang.to_owned()],
+            };
+            for l in expanded {
+                if !languages.contains(&l) {
+                    languages.push(l);
+                }
+            }
+        }
+        Some(languages)
+    }
 }
 
+#[allow(clippy::needless_return, clippy::useless_conversion)]
 fn get_env_languages() -> Vec<String> {
     let mut results: Vec<String> = Vec::new();
     let ignore = |lang: &String| {
@@ -169,25 +379,79 @@ pub(crate) fn get_default_pages_dir() -> Result<PathBuf> {
 
 impl From<RawConfig> for Config {
     fn from(rc: RawConfig) -> Self {
+        let mut private_pages_dirs = rc.private_pages_dirs.unwrap_or_default();
+        if let Some(path) = rc.private_pages_dir {
+            private_pages_dirs.push(PrivatePagesDir {
+                name: DEFAULT_PRIVATE_SOURCE.to_string(),
+                path,
+                priority: 0,
+                git_push: false,
+            });
+        }
+        // stable sort, keep the configured order for the same priority
+        private_pages_dirs.sort_by_key(|d| std::cmp::Reverse(d.priority));
+
         Self {
             official_pages_dir: rc.official_pages_dir,
-            private_pages_dir: rc.private_pages_dir,
+            private_pages_dirs,
             platform: rc.platform.and_then(|p| p.parse().ok()),
             languages: Vec::new(),
             styled: rc.sytled.unwrap_or_default(),
+            style: rc.style.as_ref().map(Style::from).unwrap_or_default(),
+            format: Format::default(),
+            width: None,
             editor: rc.editor,
+            pager: rc.pager,
+            no_pager: false,
+            archive_url: rc.archive_url,
+            archive_mirrors: rc.archive_mirrors.unwrap_or_default(),
+            archive_sha256: rc.archive_sha256,
+            require_checksum: rc.require_checksum.unwrap_or_default(),
+            archive_public_key: rc.archive_public_key,
+            languages_to_cache: rc.languages_to_cache,
+            connect_timeout_secs: rc.connect_timeout_secs,
+            read_timeout_secs: rc.read_timeout_secs,
+            download_retries: rc.download_retries,
+            cache_max_age_days: rc.cache_max_age_days,
+            auto_update: rc.auto_update.unwrap_or_default(),
         }
     }
 }
 
 
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PrivatePagesDir {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub priority: i32,
+    /// Commit and push pages to upstream after editing, git checkout only.
+    #[serde(default)]
+    pub git_push: bool,
+}
+
+
 #[derive(Debug, Deserialize)]
 struct RawConfig {
     pub official_pages_dir: Option<PathBuf>,
     pub private_pages_dir: Option<PathBuf>,
+    pub private_pages_dirs: Option<Vec<PrivatePagesDir>>,
     pub platform: Option<String>,
     pub sytled: Option<StyledChoice>,
+    pub style: Option<RawStyle>,
     pub editor: Option<String>,
+    pub pager: Option<String>,
+    pub archive_url: Option<String>,
+    pub archive_mirrors: Option<Vec<String>>,
+    pub archive_sha256: Option<String>,
+    pub require_checksum: Option<bool>,
+    pub archive_public_key: Option<String>,
+    pub languages_to_cache: Option<Vec<String>>,
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    pub download_retries: Option<u32>,
+    pub cache_max_age_days: Option<u64>,
+    pub auto_update: Option<AutoUpdate>,
 }
 
 
@@ -195,13 +459,108 @@ struct RawConfig {
 #[cfg(test)]
 mod test {
 
+    mod args {
+        use std::path::Path;
+
+        use clap::Parser;
+
+        use crate::config::Args;
+
+        #[test]
+        fn export_is_a_command() {
+            let args = Args::try_parse_from(["tldrx", "export"]).unwrap();
+            assert_eq!(args.command, vec!["export"]);
+            assert_eq!(args.export_html, None);
+        }
+
+        #[test]
+        fn export_html_with_source() {
+            let args = Args::try_parse_from(["tldrx", "--export-html", "site", "--source", "team"]).unwrap();
+            assert_eq!(args.export_html.as_deref(), Some(Path::new("site")));
+            assert_eq!(args.source.as_deref(), Some("team"));
+            assert!(Args::try_parse_from(["tldrx", "--source", "team", "tar"]).is_err());
+        }
+    }
+
+    mod private_pages_dirs {
+        use crate::config::{Config, RawConfig};
+
+        fn load(content: &str) -> Config {
+            let raw_config: RawConfig = toml::from_str(content).unwrap();
+            raw_config.into()
+        }
+
+        fn names(config: &Config) -> Vec<&str> {
+            config.private_pages_dirs.iter().map(|d| d.name.as_str()).collect()
+        }
+
+        #[test]
+        fn legacy_single_dir() {
+            let config = load(r#"private_pages_dir = "/pages""#);
+            assert_eq!(names(&config), vec!["private"]);
+        }
+
+        #[test]
+        fn sorted_by_priority() {
+            let config = load(r#"
+                private_pages_dir = "/legacy"
+
+                [[private_pages_dirs]]
+                name = "team"
+                path = "/team"
+
+                [[private_pages_dirs]]
+                name = "personal"
+                path = "/personal"
+                priority = 10
+
+                [[private_pages_dirs]]
+                name = "project"
+                path = "/project"
+                priority = 5
+            "#);
+            assert_eq!(names(&config), vec!["personal", "project", "team", "private"]);
+            assert_eq!(config.get_private_pages_dir(None).unwrap().name, "personal");
+            assert_eq!(config.get_private_pages_dir(Some("team")).unwrap().name, "team");
+            assert!(config.get_private_pages_dir(Some("missing")).is_err());
+        }
+    }
+
+    mod archive_urls {
+        use crate::config::{Config, OFFICIAL_PAGES_ARCHIVE_URL};
+
+        #[test]
+        fn official_by_default() {
+            let config = Config::default();
+            assert_eq!(config.get_archive_urls(), vec![OFFICIAL_PAGES_ARCHIVE_URL]);
+        }
+
+        #[test]
+        fn archive_url_then_mirrors() {
+            let config = Config {
+                archive_url: Some("https://mirror.example.com/tldr.zip".into()),
+                archive_mirrors: vec!["file:///srv/tldr.zip".into()],
+                ..Default::default()
+            };
+            assert_eq!(
+                config.get_archive_urls(),
+                vec!["https://mirror.example.com/tldr.zip", "file:///srv/tldr.zip"]
+            );
+            assert_eq!(
+                config.get_language_archive_urls("zh"),
+                vec!["https://mirror.example.com/tldr-pages.zh.zip", "file:///srv/tldr-pages.zh.zip"]
+            );
+        }
+    }
+
+    #[allow(clippy::zero_repeat_side_effects)]
     mod language {
         use std::env;
         use std::sync::Mutex;
 
         use lazy_static::lazy_static;
 
-        use crate::config::get_env_languages;
+        use crate::config::{get_env_languages, Config};
 
         const LANG: &str = "LANG";
         const LANGUAGE: &str = "LANGUAGE";
@@ -271,5 +630,25 @@ mod test {
                 assert_eq!(get_env_languages(), vec![String::new(); 0]);
             });
         }
+
+        #[test]
+        fn languages_to_cache() {
+            clean_langs_env_run(|| {
+                env::set_var(LANG, "zh_TW");
+                let config = |langs: &[&str]| Config {
+                    languages_to_cache: Some(langs.iter().map(|l| l.to_string()).collect()),
+                    ..Default::default()
+                };
+                assert_eq!(Config::default().get_languages_to_cache(), None);
+                assert_eq!(config(&[]).get_languages_to_cache().unwrap(), vec!["en"]);
+                assert_eq!(config(&["ja", "en"]).get_languages_to_cache().unwrap(), vec!["en", "ja"]);
+                assert_eq!(
+                    config(&["auto", "ja"]).get_languages_to_cache().unwrap(),
+                    vec!["en", "zh_TW", "zh", "ja"]
+                );
+                env::set_var(LANG, "en_US.UTF-8");
+                assert_eq!(config(&["auto"]).get_languages_to_cache().unwrap(), vec!["en"]);
+            });
+        }
     }
 }
@@ -0,0 +1,650 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, stderr, ErrorKind, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::tty::IsTty;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
+
+use crate::config::Config;
+use crate::meta::ArchiveMeta;
+use crate::verify;
+
+
+const ARCHIVE_FILE: &str = "tldr.zip";
+/// Delay before the first retry, doubled for each next one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const CHUNK_SIZE: usize = 64 * 1024;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+/// Progress lines when stderr is not a terminal, often enough to tell it is not hung.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+const BAR_WIDTH: usize = 24;
+
+
+/// A downloaded and verified archive.
+pub(crate) struct Download {
+    pub archive: PathBuf,
+    pub meta: ArchiveMeta,
+}
+
+/// No link has the archive of a language, tldr-pages publishes only some of them.
+#[derive(Debug)]
+pub(crate) struct NotPublished(pub String);
+
+impl fmt::Display for NotPublished {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No pages archive of language {} is published", self.0)
+    }
+}
+
+impl Error for NotPublished {}
+
+/// A `file://` link to a missing file, like a 404 of a web one.
+#[derive(Debug)]
+struct FileNotFound(PathBuf);
+
+impl fmt::Display for FileNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "File not found: {}", self.0.display())
+    }
+}
+
+impl Error for FileNotFound {}
+
+enum Fetched {
+    Modified { etag: Option<String>, last_modified: Option<String> },
+    NotModified,
+}
+
+/// Download the archive of `language`, or the one of all languages, from the first link
+/// that works. `None` if the `cached` one is not modified since, as told by its `ETag` or
+/// `Last-Modified`. Fails with [`NotPublished`] if every link of a language is not found.
+pub(crate) fn download_archive(
+    config: &Config,
+    language: Option<&str>,
+    cached: Option<&ArchiveMeta>,
+) -> Result<Option<Download>> {
+    let (archive, urls) = match language {
+        None => (ARCHIVE_FILE.to_string(), config.get_archive_urls().into_iter().map(String::from).collect()),
+        Some(lang) => (format!("tldr-pages.{}.zip", lang), config.get_language_archive_urls(lang)),
+    };
+    let archive = config.get_official_page_dir()?.join(archive);
+    // a pinned checksum is of the archive of all languages
+    let pinned = config.archive_sha256.as_deref().filter(|_| language.is_none());
+
+    let mut errors = Vec::new();
+    let mut not_found = 0;
+    for url in &urls {
+        // validators only hold for the link they come from
+        let cached = cached.filter(|m| m.source_url == *url);
+        let fetched = fetch_archive(url, &archive, cached, config);
+        let missing = fetched.as_ref().err().is_some_and(is_not_found);
+        not_found += usize::from(missing);
+        let downloaded = fetched.and_then(|fetched| match fetched {
+            Fetched::NotModified => Ok(None),
+            Fetched::Modified { etag, last_modified } => {
+                let sha256 = verify::verify(&archive, url, pinned, config)?;
+                let meta = ArchiveMeta {
+                    language: language.map(String::from),
+                    source_url: url.to_owned(),
+                    sha256,
+                    etag,
+                    last_modified,
+                };
+                Ok(Some(Download { archive: archive.clone(), meta }))
+            }
+        });
+        match downloaded {
+            Ok(None) => {
+                eprintln!("Pages archive not modified since last update: {}", url);
+                return Ok(None);
+            }
+            Ok(Some(download)) => {
+                eprintln!("Downloaded pages archive from: {}", url);
+                return Ok(Some(download));
+            }
+            Err(e) => {
+                // expected of languages tldr-pages has no pages for, not worth a word
+                if !(missing && language.is_some()) {
+                    eprintln!("Fail to download archive from: {} ({:#})", url, e);
+                }
+                errors.push(format!("  * {}: {:#}", url, e));
+            }
+        }
+    }
+    // never leave an unverified archive around
+    let _ = fs::remove_file(&archive);
+    if let Some(lang) = language.filter(|_| not_found == urls.len()) {
+        return Err(NotPublished(lang.to_string()).into());
+    }
+    Err(anyhow!("Fail to download archive from any source:\n{}", errors.join("\n")))
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<FileNotFound>().is_some()
+        || e.downcast_ref::<reqwest::Error>().and_then(reqwest::Error::status) == Some(StatusCode::NOT_FOUND)
+}
+
+fn fetch_archive(url: &str, archive: &Path, cached: Option<&ArchiveMeta>, config: &Config) -> Result<Fetched> {
+    let mut file = File::create(archive)
+        .with_context(|| format!("Fail to create archive: {}", archive.display()))?;
+    let fetched = fetch_if_modified(url, cached, &mut file, true, config)?;
+    drop(file);
+    if let Fetched::NotModified = fetched {
+        let _ = fs::remove_file(archive);
+    }
+    Ok(fetched)
+}
+
+/// Copy the content of a `http(s)://` or `file://` link to `out`.
+pub(crate) fn fetch(url: &str, out: &mut Vec<u8>, config: &Config) -> Result<()> {
+    fetch_if_modified(url, None, out, false, config).map(|_| ())
+}
+
+/// Where a download goes, emptied to start over on retries.
+trait Sink: Write {
+    fn reset(&mut self) -> io::Result<()>;
+}
+
+impl Sink for Vec<u8> {
+    fn reset(&mut self) -> io::Result<()> {
+        self.clear();
+        Ok(())
+    }
+}
+
+impl Sink for File {
+    fn reset(&mut self) -> io::Result<()> {
+        self.set_len(0)?;
+        self.rewind()
+    }
+}
+
+/// Like [`fetch`], but ask the server for nothing if the content is the same as `cached`.
+/// Transient failures are retried with backoff.
+fn fetch_if_modified<S: Sink>(
+    url: &str,
+    cached: Option<&ArchiveMeta>,
+    out: &mut S,
+    progress: bool,
+    config: &Config,
+) -> Result<Fetched> {
+    let parsed = Url::parse(url).with_context(|| format!("Invalid url: {}", url))?;
+    if parsed.scheme() == "file" {
+        let src = parsed.to_file_path().map_err(|_| anyhow!("Invalid file url: {}", url))?;
+        let mut file = match File::open(&src) {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(FileNotFound(src).into()),
+            opened => opened.with_context(|| format!("Could not open file: {}", src.display()))?,
+        };
+        io::copy(&mut file, out).with_context(|| format!("Fail to copy: {}", src.display()))?;
+        return Ok(Fetched::Modified { etag: None, last_modified: None });
+    }
+
+    let client = Client::builder()
+        .connect_timeout(config.get_connect_timeout())
+        // the blocking client applies it to every wait for data, not the whole download
+        .timeout(config.get_read_timeout())
+        .build()
+        .context("Fail to set up HTTP client")?;
+    let retries = config.get_download_retries();
+    let mut attempt = 0;
+    loop {
+        match try_fetch(&client, &parsed, cached, out, progress) {
+            Ok(fetched) => return Ok(fetched),
+            Err((failure, e)) if failure.is_transient() && attempt < retries => {
+                attempt += 1;
+                let delay = (RETRY_DELAY * 2u32.pow(attempt - 1)).min(MAX_RETRY_DELAY);
+                eprintln!("{:#}, retry {}/{} in {}s", e, attempt, retries, delay.as_secs());
+                thread::sleep(delay);
+                out.reset().context("Fail to start the download over")?;
+            }
+            Err((_, e)) => return Err(e),
+        }
+    }
+}
+
+fn try_fetch<S: Sink>(
+    client: &Client,
+    url: &Url,
+    cached: Option<&ArchiveMeta>,
+    out: &mut S,
+    progress: bool,
+) -> Result<Fetched, (Failure, anyhow::Error)> {
+    let host = url.host_str().unwrap_or_default();
+    let fail = |failure: Failure, e: anyhow::Error| (failure, e.context(failure.describe(host)));
+    let classify = |e: reqwest::Error| fail(Failure::of(&e, url), e.into());
+
+    let mut request = client.get(url.clone());
+    if let Some(etag) = cached.and_then(|m| m.etag.as_ref()) {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = cached.and_then(|m| m.last_modified.as_ref()) {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let resp = request.send().map_err(classify)?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    let mut resp = resp.error_for_status().map_err(classify)?;
+    let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+    let mut progress = progress.then(|| Progress::new(url.as_str(), resp.content_length()));
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = match read(&mut resp, &mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if is_timeout(&e) => return Err(fail(Failure::Timeout, e.into())),
+            Err(e) => return Err(fail(Failure::Body, e.into())),
+        };
+        out.write_all(&buf[..n])
+            .map_err(|e| (Failure::Other, anyhow!(e).context("Fail to write download")))?;
+        if let Some(ref mut p) = progress {
+            p.advance(n as u64);
+        }
+    }
+    out.flush().map_err(|e| (Failure::Other, anyhow!(e).context("Fail to write download")))?;
+    if let Some(ref mut p) = progress {
+        p.finish();
+    }
+    Ok(Fetched::Modified { etag, last_modified })
+}
+
+fn read(resp: &mut Response, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match resp.read(buf) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            r => return r,
+        }
+    }
+}
+
+/// The blocking client hands a timeout while reading the body as an io error of any kind,
+/// wrapping its own.
+fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == ErrorKind::TimedOut
+        || e.get_ref().and_then(|e| e.downcast_ref::<reqwest::Error>()).is_some_and(reqwest::Error::is_timeout)
+}
+
+/// What went wrong with a request, told apart to give a hint and to retry only the
+/// failures that may go away.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Failure {
+    Dns,
+    Tls,
+    Proxy,
+    Timeout,
+    Connect,
+    Status(StatusCode),
+    /// The connection broke off in the middle of the response.
+    Body,
+    Other,
+}
+
+impl Failure {
+    fn of(e: &reqwest::Error, url: &Url) -> Self {
+        if let Some(status) = e.status() {
+            return Failure::Status(status);
+        }
+        if e.is_timeout() {
+            return Failure::Timeout;
+        }
+        let mut messages = Vec::new();
+        let mut source: Option<&(dyn Error + 'static)> = Some(e);
+        while let Some(err) = source {
+            messages.push(err.to_string().to_lowercase());
+            source = err.source();
+        }
+        let mentions = |s: &str| messages.iter().any(|m| m.contains(s));
+        // hyper tells failures of resolving and of TCP, connected but failed is the handshake
+        let is_dns = mentions("dns error") || mentions("failed to lookup address");
+        let is_tls = e.is_connect() && url.scheme() == "https" && !is_dns && !mentions("tcp ");
+
+        if mentions("proxy") || mentions("tunnel") {
+            Failure::Proxy
+        } else if is_tls {
+            Failure::Tls
+        } else if e.is_connect() && proxy_for(url, |k| env::var(k).ok()).is_some() {
+            Failure::Proxy
+        } else if is_dns {
+            Failure::Dns
+        } else if e.is_connect() {
+            Failure::Connect
+        } else if e.is_body() || e.is_decode() {
+            Failure::Body
+        } else {
+            Failure::Other
+        }
+    }
+
+    fn is_transient(self) -> bool {
+        match self {
+            Failure::Timeout | Failure::Connect | Failure::Body => true,
+            Failure::Status(s) => {
+                s.is_server_error() || s == StatusCode::TOO_MANY_REQUESTS || s == StatusCode::REQUEST_TIMEOUT
+            }
+            _ => false,
+        }
+    }
+
+    fn describe(self, host: &str) -> String {
+        match self {
+            Failure::Dns => format!("DNS lookup of {} failed", host),
+            Failure::Tls => format!("TLS handshake with {} failed", host),
+            Failure::Proxy => format!("Proxy failed to reach {}", host),
+            Failure::Timeout => format!("Timed out waiting for {}", host),
+            Failure::Connect => format!("Could not connect to {}", host),
+            Failure::Status(s) => format!("{} responded with HTTP {}", host, s),
+            Failure::Body => format!("Connection to {} broke off", host),
+            Failure::Other => format!("Request to {} failed", host),
+        }
+    }
+}
+
+/// The proxy the environment sets for `url`, as read by `var`.
+fn proxy_for(url: &Url, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let var = |name: &str| var(name).or_else(|| var(&name.to_lowercase())).filter(|v| !v.is_empty());
+    let host = url.host_str().unwrap_or_default();
+    let bypassed = var("NO_PROXY").is_some_and(|no_proxy| {
+        no_proxy.split(',').map(str::trim).filter(|p| !p.is_empty()).any(|p| {
+            let p = p.trim_start_matches('.');
+            p == "*" || host == p || host.ends_with(&format!(".{}", p))
+        })
+    });
+    if bypassed {
+        return None;
+    }
+    match url.scheme() {
+        "https" => var("HTTPS_PROXY"),
+        _ => var("HTTP_PROXY"),
+    }
+    .or_else(|| var("ALL_PROXY"))
+}
+
+/// Download progress on stderr, a bar redrawn in place on a terminal, or a line now and
+/// then otherwise.
+struct Progress {
+    name: String,
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    shown: Instant,
+    tty: bool,
+    finished: bool,
+}
+
+impl Progress {
+    fn new(url: &str, total: Option<u64>) -> Self {
+        let name = url.rsplit('/').next().unwrap_or(url).to_string();
+        let tty = stderr().is_tty();
+        if !tty {
+            let size = total.map_or_else(|| "unknown size".to_string(), format_size);
+            eprintln!("Downloading {} ({})", url, size);
+        }
+        let now = Instant::now();
+        Self { name, total, done: 0, started: now, shown: now, tty, finished: false }
+    }
+
+    fn advance(&mut self, n: u64) {
+        self.done += n;
+        let interval = if self.tty { REDRAW_INTERVAL } else { LOG_INTERVAL };
+        if self.shown.elapsed() >= interval {
+            self.show();
+            self.shown = Instant::now();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        if self.tty {
+            self.show();
+            eprintln!();
+        } else {
+            let secs = self.started.elapsed().as_secs_f64();
+            eprintln!("  {} in {:.1}s, {}/s", format_size(self.done), secs, format_size(self.rate()));
+        }
+    }
+
+    fn show(&self) {
+        if self.tty {
+            let bar = match self.total {
+                Some(total) if total > 0 => {
+                    let filled = (self.done.min(total) * BAR_WIDTH as u64 / total) as usize;
+                    format!("[{}{}] ", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+                }
+                _ => String::new(),
+            };
+            // back to the line start and clear it
+            let _ = queue!(
+                stderr(),
+                Print('\r'),
+                Clear(ClearType::CurrentLine),
+                Print(format!("{} {}{}", self.name, bar, self.status()))
+            );
+        } else {
+            eprintln!("  {}", self.status());
+        }
+    }
+
+    /// In bytes per second.
+    fn rate(&self) -> u64 {
+        (self.done as f64 / self.started.elapsed().as_secs_f64().max(0.001)) as u64
+    }
+
+    /// `done / total  rate`
+    fn status(&self) -> String {
+        let rate = format_size(self.rate());
+        match self.total {
+            Some(total) => format!("{} / {}  {}/s", format_size(self.done), format_size(total), rate),
+            None => format!("{}  {}/s", format_size(self.done), rate),
+        }
+    }
+}
+
+impl Drop for Progress {
+    /// Leave the bar of a failed download on its own line, before the error.
+    fn drop(&mut self) {
+        if self.tty && !self.finished {
+            eprintln!();
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const LAST_MODIFIED_AT: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+    /// A stand-in HTTP server answering each request, its head lines given, with `respond`.
+    /// Returns the address it listens to.
+    pub(crate) fn listen(respond: impl Fn(&[String]) -> Vec<u8> + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                let head = BufReader::new(&stream).lines()
+                    .map_while(Result::ok)
+                    .take_while(|l| !l.is_empty())
+                    .collect::<Vec<_>>();
+                let _ = stream.write_all(&respond(&head));
+            }
+        });
+        addr
+    }
+
+    pub(crate) fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut r = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+            status, body.len(), headers,
+        ).into_bytes();
+        r.extend(body);
+        r
+    }
+
+    /// Serve `/tldr.zip` with the `ETag` of its content, 404 for anything else. Returns the
+    /// archive link and the requests it received.
+    fn serve(archive: Arc<Mutex<Vec<u8>>>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+        let addr = listen(move |head| {
+            let header = |name: &str| head.iter()
+                .find_map(|l| l.split_once(": ").filter(|(n, _)| n.eq_ignore_ascii_case(name)))
+                .map(|(_, v)| v.to_string());
+            received.lock().unwrap().push(head.join("\n"));
+
+            let body = archive.lock().unwrap().clone();
+            let etag = format!("\"{}\"", body.len());
+            if !head.first().is_some_and(|l| l.starts_with(&format!("GET /{} ", ARCHIVE_FILE))) {
+                response("404 Not Found", "", b"")
+            } else if header("If-None-Match").as_ref() == Some(&etag) {
+                b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_vec()
+            } else {
+                let headers = format!("ETag: {}\r\nLast-Modified: {}\r\n", etag, LAST_MODIFIED_AT);
+                response("200 OK", &headers, &body)
+            }
+        });
+        (format!("http://{}/{}", addr, ARCHIVE_FILE), requests)
+    }
+
+    #[test]
+    fn conditional_download() {
+        let tmp = tempfile::tempdir().unwrap();
+        let body = Arc::new(Mutex::new(b"first".to_vec()));
+        let (url, requests) = serve(Arc::clone(&body));
+        let config = Config {
+            official_pages_dir: Some(tmp.path().to_owned()),
+            archive_url: Some(url.clone()),
+            ..Default::default()
+        };
+
+        let download = download_archive(&config, None, None).unwrap().unwrap();
+        assert_eq!(fs::read(&download.archive).unwrap(), b"first");
+        let meta = download.meta;
+        assert_eq!(meta.source_url, url);
+        assert_eq!(meta.etag.as_deref(), Some("\"5\""));
+        assert_eq!(meta.last_modified.as_deref(), Some(LAST_MODIFIED_AT));
+
+        assert!(download_archive(&config, None, Some(&meta)).unwrap().is_none());
+        assert!(!tmp.path().join(ARCHIVE_FILE).exists());
+        let last = requests.lock().unwrap().last().unwrap().to_lowercase();
+        assert!(last.contains("if-none-match: \"5\""));
+        assert!(last.contains(&format!("if-modified-since: {}", LAST_MODIFIED_AT.to_lowercase())));
+
+        *body.lock().unwrap() = b"second".to_vec();
+        let download = download_archive(&config, None, Some(&meta)).unwrap().unwrap();
+        assert_eq!(fs::read(&download.archive).unwrap(), b"second");
+        assert_eq!(download.meta.etag.as_deref(), Some("\"6\""));
+    }
+
+    #[test]
+    fn retry_transient_failures() {
+        let count = Arc::new(Mutex::new(0));
+        let served = Arc::clone(&count);
+        let addr = listen(move |_| {
+            let mut count = served.lock().unwrap();
+            *count += 1;
+            match *count {
+                1 | 2 => response("503 Service Unavailable", "", b""),
+                _ => response("200 OK", "", b"pages"),
+            }
+        });
+        let url = format!("http://{}/{}", addr, ARCHIVE_FILE);
+
+        let config = Config { download_retries: Some(0), ..Default::default() };
+        let e = fetch(&url, &mut Vec::new(), &config).unwrap_err();
+        assert!(format!("{:#}", e).contains("responded with HTTP 503 Service Unavailable"));
+
+        let config = Config { download_retries: Some(1), ..Default::default() };
+        let mut out = Vec::new();
+        fetch(&url, &mut out, &config).unwrap();
+        assert_eq!(out, b"pages");
+        assert_eq!(*count.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn classified_failures() {
+        let (url, _) = serve(Arc::new(Mutex::new(Vec::new())));
+        let config = Config { download_retries: Some(0), read_timeout_secs: Some(1), ..Default::default() };
+        let error = |url: &str| format!("{:#}", fetch(url, &mut Vec::new(), &config).unwrap_err());
+
+        assert!(error(&format!("{}.sha256", url)).starts_with("127.0.0.1 responded with HTTP 404 Not Found"));
+        // plain HTTP where TLS is expected
+        assert!(error(&url.replace("http://", "https://")).starts_with("TLS handshake with 127.0.0.1 failed"));
+
+        let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = silent.local_addr().unwrap();
+        thread::spawn(move || silent.incoming().map_while(Result::ok).collect::<Vec<_>>());
+        assert!(error(&format!("http://{}/tldr.zip", addr)).starts_with("Timed out waiting for 127.0.0.1"));
+
+        // headers and a part of the body, then nothing
+        let stalled = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = stalled.local_addr().unwrap();
+        thread::spawn(move || {
+            for mut stream in stalled.incoming().map_while(Result::ok) {
+                let _ = BufReader::new(&stream).lines().map_while(Result::ok).take_while(|l| !l.is_empty()).count();
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npart");
+                thread::sleep(Duration::from_secs(5));
+            }
+        });
+        assert!(error(&format!("http://{}/tldr.zip", addr)).starts_with("Timed out waiting for 127.0.0.1"));
+
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        assert!(error(&format!("http://{}/tldr.zip", closed)).starts_with("Could not connect to 127.0.0.1"));
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let env = |vars: &[(&str, &str)]| {
+            let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>();
+            move |k: &str| vars.get(k).cloned()
+        };
+        let url = Url::parse("https://tldr.sh/assets/tldr.zip").unwrap();
+        assert_eq!(proxy_for(&url, env(&[])), None);
+        assert_eq!(proxy_for(&url, env(&[("https_proxy", "http://proxy:8080")])).unwrap(), "http://proxy:8080");
+        assert_eq!(proxy_for(&url, env(&[("HTTP_PROXY", "http://proxy:8080")])), None);
+        assert_eq!(proxy_for(&url, env(&[("ALL_PROXY", "socks5://proxy:1080")])).unwrap(), "socks5://proxy:1080");
+        let bypassed = env(&[("HTTPS_PROXY", "http://proxy:8080"), ("NO_PROXY", "localhost, .tldr.sh")]);
+        assert_eq!(proxy_for(&url, bypassed), None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(10 * 1024 * 1024 + 1024 * 102), "10.1 MiB");
+    }
+}
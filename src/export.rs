@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use crate::cache;
+use crate::config::Config;
+use crate::page::{Kind, Page, PageDoc, Segment};
+
+
+const STYLE_CSS: &str = "\
+body { margin: 0 auto; max-width: 56em; padding: 1em; font-family: sans-serif; line-height: 1.5; color: #222; }
+a { color: #0550ae; text-decoration: none; }
+a:hover { text-decoration: underline; }
+nav { margin-bottom: 1em; color: #666; }
+h1 { margin-bottom: 0.2em; }
+.meta { font-size: 0.9em; color: #fff; background: #1a7f37; padding: 0.1em 0.5em; border-radius: 0.3em; }
+.private .meta { background: #cf222e; }
+article { margin-bottom: 3em; }
+blockquote { margin: 1em 0; padding-left: 1em; border-left: 0.25em solid #ddd; color: #555; }
+blockquote p { margin: 0; }
+.example { margin: 1.2em 0 0.4em; font-weight: bold; color: #0550ae; }
+pre { margin: 0; padding: 0.6em 1em; background: #f6f8fa; border-radius: 0.3em; overflow-x: auto; }
+code { color: #1a7f37; font-weight: bold; }
+var { color: #8250df; font-style: italic; }
+ul.pages { list-style: none; padding: 0; }
+ul.pages li { margin: 0.3em 0; }
+.description { color: #555; }
+#search { width: 100%; padding: 0.5em; font-size: 1.1em; box-sizing: border-box; }
+";
+
+const SEARCH_JS: &str = r#"(function () {
+    var input = document.getElementById("search");
+    var results = document.getElementById("results");
+    input.addEventListener("input", function () {
+        var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+        results.textContent = "";
+        if (terms.length === 0) {
+            return;
+        }
+        SEARCH_INDEX.filter(function (page) {
+            var text = [page.name, page.description, page.examples].join(" ").toLowerCase();
+            return terms.every(function (term) { return text.indexOf(term) >= 0; });
+        }).slice(0, 100).forEach(function (page) {
+            var item = document.createElement("li");
+            var link = document.createElement("a");
+            link.href = page.url;
+            link.textContent = page.name;
+            var description = document.createElement("span");
+            description.className = "description";
+            description.textContent = " (" + page.lang + " :: " + page.platform + ") " + page.description;
+            item.appendChild(link);
+            item.appendChild(description);
+            results.appendChild(item);
+        });
+    });
+})();
+"#;
+
+
+/// Pages of the same name in a language and platform, one for each source.
+type Group<'a> = BTreeMap<String, Vec<(Page<'a>, PageDoc)>>;
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    name: &'a str,
+    lang: &'a str,
+    platform: &'a str,
+    description: &'a str,
+    examples: String,
+    url: String,
+}
+
+/// Export pages of all languages and platforms to a static HTML site under `outdir`, with
+/// index pages and a client-side search. Only pages of `source` are exported if given,
+/// `official` or the name of a private pages dir.
+pub(crate) fn export_html(outdir: &Path, source: Option<&str>, config: &Config) -> Result<()> {
+    let sources = cache::get_sources(config)?;
+    if let Some(source) = source {
+        if !sources.iter().any(|(_, kind)| kind.to_string() == source) {
+            let names = sources.iter().map(|(_, kind)| kind.to_string()).collect::<Vec<_>>();
+            return Err(anyhow!("Unknown pages source: {}. Choice: [{}]", source, names.join(" | ")));
+        }
+    }
+    fs::create_dir_all(outdir).with_context(|| format!("Failed to create dir: {}", outdir.display()))?;
+
+    let mut groups: BTreeMap<(String, String), Group> = BTreeMap::new();
+    let mut count = 0;
+    for (root, kind) in sources {
+        if source.is_some_and(|s| s != kind.to_string()) {
+            continue;
+        }
+        for file in cache::scan(&root) {
+            let page = match Page::option_from(file.path(&root), kind.clone(), file.platform.clone(), config) {
+                Some(p) => p,
+                None => continue,
+            };
+            let doc = match page.parse() {
+                Ok(doc) => doc,
+                Err(e) => {
+                    eprintln!("Skip page in export: {:#}", e);
+                    continue;
+                }
+            };
+            let key = (page.language().to_string(), page.platform.to_string());
+            groups.entry(key).or_default().entry(file.name).or_default().push((page, doc));
+            count += 1;
+        }
+    }
+
+    let mut search_index = Vec::new();
+    for ((lang, platform), group) in &groups {
+        let dir = outdir.join(lang).join(platform);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create dir: {}", dir.display()))?;
+        for (name, pages) in group {
+            write(&dir.join(format!("{}.html", name)), &render_page(name, lang, platform, pages))?;
+            let doc = &pages[0].1;
+            search_index.push(SearchEntry {
+                name,
+                lang,
+                platform,
+                description: doc.description.first().map(String::as_str).unwrap_or_default(),
+                examples: pages.iter()
+                    .flat_map(|(_, d)| d.examples.iter().map(|e| e.description.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                url: format!("{}/{}/{}.html", lang, platform, name),
+            });
+        }
+        write(&dir.join("index.html"), &render_group(lang, platform, group))?;
+    }
+
+    write(&outdir.join("index.html"), &render_index(&groups))?;
+    write(&outdir.join("style.css"), STYLE_CSS)?;
+    write(&outdir.join("search.js"), SEARCH_JS)?;
+    // a script rather than JSON, browsers refuse to fetch `file://` links
+    write(
+        &outdir.join("search-index.js"),
+        &format!("var SEARCH_INDEX = {};\n", serde_json::to_string(&search_index)?),
+    )?;
+    println!("Exported {} pages to: {}", count, outdir.display());
+    Ok(())
+}
+
+fn write(file: &Path, content: &str) -> Result<()> {
+    fs::write(file, content).with_context(|| format!("Failed to write: {}", file.display()))
+}
+
+fn render_index(groups: &BTreeMap<(String, String), Group>) -> String {
+    let mut body = String::from(concat!(
+        "<h1>tldr pages</h1>\n",
+        "<input id=\"search\" type=\"search\" placeholder=\"Search pages\" autofocus>\n",
+        "<ul id=\"results\" class=\"pages\"></ul>\n",
+    ));
+    let mut last_lang = None;
+    for (lang, platform) in groups.keys() {
+        if last_lang != Some(lang) {
+            if last_lang.is_some() {
+                body.push_str("</ul>\n");
+            }
+            let _ = writeln!(body, "<h2>{}</h2>\n<ul class=\"pages\">", escape(lang));
+            last_lang = Some(lang);
+        }
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{lang}/{platform}/index.html\">{platform}</a> <span class=\"description\">({} pages)</span></li>",
+            groups[&(lang.to_owned(), platform.to_owned())].len(),
+            lang = escape(lang),
+            platform = escape(platform),
+        );
+    }
+    if last_lang.is_some() {
+        body.push_str("</ul>\n");
+    }
+    body.push_str("<script src=\"search-index.js\"></script>\n<script src=\"search.js\"></script>\n");
+    layout("en", "tldr pages", "", "", &body)
+}
+
+fn render_group(lang: &str, platform: &str, group: &Group) -> String {
+    let mut body = format!("<h1>{}</h1>\n<ul class=\"pages\">\n", escape(platform));
+    for (name, pages) in group {
+        let description = pages[0].1.description.first().map(String::as_str).unwrap_or_default();
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{name}.html\">{name}</a> <span class=\"description\">{}</span></li>",
+            escape(description),
+            name = escape(name),
+        );
+    }
+    body.push_str("</ul>\n");
+    let nav = format!("<a href=\"../../index.html\">tldr pages</a> / {}", escape(lang));
+    layout(lang, &format!("{} :: {}", platform, lang), "../../", &nav, &body)
+}
+
+fn render_page(name: &str, lang: &str, platform: &str, pages: &[(Page, PageDoc)]) -> String {
+    let mut body = String::new();
+    for (page, doc) in pages {
+        let class = match page.kind {
+            Kind::Official => "official",
+            Kind::Private(_) => "private",
+        };
+        let _ = writeln!(
+            body,
+            "<article class=\"{}\">\n<h1>{}</h1>\n<span class=\"meta\">{} :: {}</span>",
+            class,
+            escape(&doc.title),
+            escape(&page.kind.to_string()),
+            escape(platform),
+        );
+        body.push_str("<blockquote>\n");
+        for line in &doc.description {
+            let _ = writeln!(body, "<p>{}</p>", escape(line));
+        }
+        if let (Some(url), Some(text)) = (&doc.more_info, &doc.more_info_text) {
+            // private pages could link to `javascript:`, only web links are clickable
+            if matches!(url.scheme(), "http" | "https") {
+                let _ = writeln!(
+                    body,
+                    "<p>More information: <a href=\"{}\">{}</a>.</p>",
+                    escape(url.as_str()),
+                    escape(text)
+                );
+            } else {
+                let _ = writeln!(body, "<p>More information: {}.</p>", escape(text));
+            }
+        }
+        body.push_str("</blockquote>\n");
+        for example in &doc.examples {
+            if !example.description.is_empty() {
+                let _ = writeln!(body, "<p class=\"example\">{}</p>", escape(&example.description));
+            }
+            if example.command.is_empty() {
+                continue;
+            }
+            body.push_str("<pre><code>");
+            for segment in &example.command {
+                match segment {
+                    Segment::Normal(c) => body.push_str(&escape(c)),
+                    Segment::Token(c) => { let _ = write!(body, "<var>{}</var>", escape(c)); }
+                }
+            }
+            body.push_str("</code></pre>\n");
+        }
+        body.push_str("</article>\n");
+    }
+    let nav = format!(
+        "<a href=\"../../index.html\">tldr pages</a> / <a href=\"index.html\">{} :: {}</a>",
+        escape(platform),
+        escape(lang)
+    );
+    layout(lang, name, "../../", &nav, &body)
+}
+
+fn layout(lang: &str, title: &str, root: &str, nav: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"{}\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n\
+         <link rel=\"stylesheet\" href=\"{}style.css\">\n\
+         </head>\n\
+         <body>\n\
+         <nav>{}</nav>\n\
+         <main>\n{}</main>\n\
+         </body>\n\
+         </html>\n",
+        escape(lang),
+        escape(title),
+        root,
+        nav,
+        body
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::PrivatePagesDir;
+
+    fn write_page(root: &Path, lang: &str, platform: &str, name: &str, content: &str) {
+        let dir = root.join(lang).join(platform);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.md", name)), content).unwrap();
+    }
+
+    #[test]
+    fn export_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let official = tmp.path().join("cache");
+        let private = tmp.path().join("private");
+        let outdir = tmp.path().join("site");
+        let tar = "# tar\n\n> Archiving <utility>.\n> More information: <https://www.gnu.org/software/tar>.\n\n\
+            - List files:\n\n`tar tf {{source.tar}}`\n";
+        write_page(&official.join(cache::PAGES_DIR), "pages", "common", "tar", tar);
+        write_page(&official.join(cache::PAGES_DIR), "pages.zh", "linux", "apt", "# apt\n\n> 软件包管理。\n");
+        write_page(&private, "pages", "common", "tar", "# tar\n\n> Team notes.\n> More information: <javascript:alert(1)>.\n");
+        write_page(&private, "pages", "common", "broken", "");
+        let config = Config {
+            official_pages_dir: Some(official),
+            private_pages_dirs: vec![PrivatePagesDir {
+                name: "team".into(),
+                path: private,
+                priority: 0,
+                git_push: false,
+            }],
+            ..Default::default()
+        };
+
+        export_html(&outdir, None, &config).unwrap();
+        let read = |path: &str| fs::read_to_string(outdir.join(path)).unwrap();
+        let page = read("en/common/tar.html");
+        assert!(page.contains("<p>Archiving &lt;utility&gt;.</p>"));
+        assert!(page.contains("<pre><code>tar tf <var>source.tar</var></code></pre>"));
+        assert!(page.contains("<p>Team notes.</p>"));
+        assert!(page.contains(
+            "<p>More information: <a href=\"https://www.gnu.org/software/tar\">https://www.gnu.org/software/tar</a>.</p>"
+        ));
+        assert!(page.contains("<p>More information: javascript:alert(1).</p>"));
+        assert!(!page.contains("href=\"javascript"));
+        assert!(!outdir.join("en/common/broken.html").exists());
+        assert!(read("zh/linux/index.html").contains("<a href=\"apt.html\">apt</a>"));
+        assert!(read("index.html").contains("<a href=\"zh/linux/index.html\">linux</a>"));
+        assert!(read("search-index.js").contains("\"url\":\"en/common/tar.html\""));
+        assert!(outdir.join("style.css").is_file());
+
+        let team = tmp.path().join("team-site");
+        export_html(&team, Some("team"), &config).unwrap();
+        let page = fs::read_to_string(team.join("en/common/tar.html")).unwrap();
+        assert!(page.contains("Team notes.") && !page.contains("Archiving"));
+        assert!(!team.join("zh").exists());
+
+        assert!(export_html(&tmp.path().join("typo-site"), Some("taem"), &config).is_err());
+        assert!(!tmp.path().join("typo-site").exists());
+        let empty = tmp.path().join("empty-site");
+        let config = Config { official_pages_dir: Some(tmp.path().join("none")), ..Default::default() };
+        export_html(&empty, None, &config).unwrap();
+        assert!(empty.join("index.html").is_file());
+    }
+}
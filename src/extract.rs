@@ -0,0 +1,233 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use zip::ZipArchive;
+
+
+/// Files kept besides `.md` pages, at the root of the archive.
+const METADATA_FILES: [&str; 1] = ["index.json"];
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+/// Symlink targets are short, anything longer is not one.
+const MAX_LINK_LEN: u64 = 4096;
+
+
+/// Bounds of what an archive may extract to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    pub max_entries: usize,
+    /// In bytes, of all extracted files together.
+    pub max_total_size: u64,
+}
+
+impl Default for Limits {
+    /// Far above the official archive of all languages, about 50k entries and 100 MiB.
+    fn default() -> Self {
+        Self { max_entries: 500_000, max_total_size: 1024 * 1024 * 1024 }
+    }
+}
+
+/// Extract the pages of `archive` into `dir`, unlike [`ZipArchive::extract`] an archive
+/// from an untrusted mirror can't write outside `dir`, or fill up the disk.
+///
+/// Entries with absolute paths or `..`, and symlinks pointing out of `dir` fail the whole
+/// extraction. Files other than `.md` pages and known metadata are skipped, so are symlinks.
+pub(crate) fn extract<R: Read + Seek>(archive: &mut ZipArchive<R>, dir: &Path, limits: Limits) -> Result<()> {
+    if archive.len() > limits.max_entries {
+        bail!("Too many entries in archive: {}, at most {}", archive.len(), limits.max_entries);
+    }
+
+    let mut total: u64 = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).context("Fail to read archive entry")?;
+        let name = entry.name().to_string();
+        let path = safe_path(&name)?;
+
+        if entry.unix_mode().is_some_and(|m| m & S_IFMT == S_IFLNK) {
+            let mut target = String::new();
+            entry.by_ref().take(MAX_LINK_LEN).read_to_string(&mut target)
+                .with_context(|| format!("Fail to read symlink in archive: {}", name))?;
+            if !is_enclosed(&path, &target) {
+                bail!("Symlink in archive points outside: {} -> {}", name, target);
+            }
+            continue;
+        }
+        if entry.is_dir() {
+            continue;
+        }
+        if !is_wanted(&path) {
+            continue;
+        }
+
+        if total.saturating_add(entry.size()) > limits.max_total_size {
+            bail!("Archive is larger than {} bytes uncompressed", limits.max_total_size);
+        }
+        let file = dir.join(&path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Fail to create directory: {}", parent.display()))?;
+        }
+        let mut out = File::create(&file).with_context(|| format!("Fail to create: {}", file.display()))?;
+        // the declared size may lie, never read more than the rest of the limit
+        let remaining = limits.max_total_size - total;
+        let written = io::copy(&mut entry.by_ref().take(remaining + 1), &mut out)
+            .with_context(|| format!("Fail to extract: {}", name))?;
+        total += written;
+        if total > limits.max_total_size {
+            bail!("Archive is larger than {} bytes uncompressed", limits.max_total_size);
+        }
+    }
+    Ok(())
+}
+
+/// The relative path of an entry, only plain components allowed.
+fn safe_path(name: &str) -> Result<PathBuf> {
+    // zip entries use `/`, but a crafted one may use `\` to sneak past on Windows
+    let path = Path::new(name.trim_end_matches('/'));
+    if name.contains('\\') || name.contains('\0') || path.as_os_str().is_empty() {
+        return Err(anyhow!("Unsafe path in archive: {}", name));
+    }
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path.to_owned())
+    } else {
+        Err(anyhow!("Unsafe path in archive: {}", name))
+    }
+}
+
+/// Whether the symlink at `link` pointing to `target` stays inside the extraction dir.
+fn is_enclosed(link: &Path, target: &str) -> bool {
+    let mut depth = link.components().count() - 1;
+    for c in Path::new(target).components() {
+        match c {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn is_wanted(path: &Path) -> bool {
+    let is_page = path.extension().is_some_and(|e| e == "md");
+    is_page || METADATA_FILES.iter().any(|f| path == Path::new(f))
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    /// Mode bits of regular files the writer stores, `0o100644 << 16` in little endian.
+    const REGULAR_FILE: [u8; 4] = [0x00, 0x00, 0xa4, 0x81];
+    /// `0o120777 << 16` in little endian.
+    const SYMLINK: [u8; 4] = [0x00, 0x00, 0xff, 0xa1];
+
+    fn zip(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        ZipArchive::new(writer.finish().unwrap()).unwrap()
+    }
+
+    /// Turn the only entry of a zip into a symlink, the writer can't make one.
+    fn symlink(name: &str, target: &str) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut bytes = zip(&[(name, target)]).into_inner().into_inner();
+        let at = bytes.windows(4).position(|w| w == REGULAR_FILE).unwrap();
+        bytes[at..at + 4].copy_from_slice(&SYMLINK);
+        ZipArchive::new(Cursor::new(bytes)).unwrap()
+    }
+
+    /// A page after another one, declaring a size near `u64::MAX` in its zip64 extra field.
+    fn oversize(name: &str) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("pages/common/a.md", FileOptions::default()).unwrap();
+        writer.write_all(b"aaaa").unwrap();
+        writer.start_file_with_extra_data(name, FileOptions::default()).unwrap();
+        // the writer refuses a zip64 field, so write it with another id
+        writer.write_all(&[0xfe, 0xca, 0x08, 0x00]).unwrap();
+        writer.write_all(&(u64::MAX - 1).to_le_bytes()).unwrap();
+        writer.end_extra_data().unwrap();
+        writer.write_all(b"bbbb").unwrap();
+        let mut bytes = writer.finish().unwrap().into_inner();
+        // then patch the id, and the size in the central directory to `0xffffffff` to use it
+        let at = bytes.windows(4).rposition(|w| w == [0xfe, 0xca, 0x08, 0x00]).unwrap();
+        bytes[at..at + 2].copy_from_slice(&[0x01, 0x00]);
+        let at = bytes.windows(4).rposition(|w| w == b"PK\x01\x02").unwrap() + 24;
+        bytes[at..at + 4].copy_from_slice(&[0xff; 4]);
+        ZipArchive::new(Cursor::new(bytes)).unwrap()
+    }
+
+    fn extract_to(archive: &mut ZipArchive<Cursor<Vec<u8>>>, limits: Limits) -> (tempfile::TempDir, Result<()>) {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("pages");
+        let result = extract(archive, &dir, limits);
+        (tmp, result)
+    }
+
+    #[test]
+    fn pages_and_metadata_only() {
+        let mut archive = zip(&[
+            ("pages/common/tar.md", "# tar"),
+            ("pages.zh/linux/apt.md", "# apt"),
+            ("index.json", "{}"),
+            ("LICENSE.md", "MIT"),
+            ("pages/common/run.sh", "rm -rf ~"),
+            ("pages/index.json", "{}"),
+        ]);
+        let (tmp, result) = extract_to(&mut archive, Limits::default());
+        result.unwrap();
+        let dir = tmp.path().join("pages");
+        assert_eq!(fs::read_to_string(dir.join("pages/common/tar.md")).unwrap(), "# tar");
+        assert!(dir.join("pages.zh/linux/apt.md").is_file());
+        assert!(dir.join("index.json").is_file());
+        assert!(dir.join("LICENSE.md").is_file());
+        assert!(!dir.join("pages/common/run.sh").exists());
+        assert!(!dir.join("pages/index.json").exists());
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        for name in ["../evil.md", "pages/../../evil.md", "/tmp/evil.md", "pages\\..\\..\\evil.md"] {
+            let (tmp, result) = extract_to(&mut zip(&[(name, "")]), Limits::default());
+            assert!(result.is_err(), "{}", name);
+            assert!(!tmp.path().join("evil.md").exists());
+        }
+    }
+
+    #[test]
+    fn symlinks() {
+        let (tmp, result) = extract_to(&mut symlink("pages/common/ls.md", "../linux/ls.md"), Limits::default());
+        result.unwrap();
+        assert!(!tmp.path().join("pages/pages/common/ls.md").exists());
+
+        for target in ["../../../etc/passwd", "/etc/passwd"] {
+            let (_tmp, result) = extract_to(&mut symlink("pages/common/ls.md", target), Limits::default());
+            assert!(result.is_err(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let entries = [("pages/common/a.md", "aaaa"), ("pages/common/b.md", "bbbb")];
+        let limits = Limits { max_entries: 2, max_total_size: 8 };
+        assert!(extract_to(&mut zip(&entries), limits).1.is_ok());
+        assert!(extract_to(&mut zip(&entries), Limits { max_entries: 1, ..limits }).1.is_err());
+        assert!(extract_to(&mut zip(&entries), Limits { max_total_size: 7, ..limits }).1.is_err());
+
+        let mut archive = oversize("pages/common/b.md");
+        assert_eq!(archive.by_index(1).unwrap().size(), u64::MAX - 1);
+        let (tmp, result) = extract_to(&mut archive, Limits::default());
+        assert!(result.is_err());
+        assert!(!tmp.path().join("pages/pages/common/b.md").exists());
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+
+
+pub(crate) fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+pub(crate) fn has_upstream(dir: &Path) -> bool {
+    run(dir, &["rev-parse", "--abbrev-ref", "@{upstream}"]).is_ok()
+}
+
+/// Pull the upstream changes, report the conflicted files and abort if the merge fails,
+/// so pages are never left with conflict markers.
+pub(crate) fn pull(dir: &Path) -> Result<()> {
+    if let Err(e) = run(dir, &["pull", "--no-rebase", "--no-edit"]) {
+        let conflicts = run(dir, &["diff", "--name-only", "--diff-filter=U"])
+            .unwrap_or_default();
+        if conflicts.is_empty() {
+            return Err(e);
+        }
+        let _ = run(dir, &["merge", "--abort"]);
+        let files = conflicts.lines().map(|f| format!("  * {}", f)).collect::<Vec<_>>();
+        return Err(anyhow!(
+            "Conflicts need to be resolved in: {}, merge aborted\n{}",
+            dir.display(),
+            files.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// Commit the given page if it has been changed, then push it to upstream.
+pub(crate) fn commit_and_push(dir: &Path, file: &Path, message: &str) -> Result<()> {
+    let file = file.strip_prefix(dir).unwrap_or(file);
+    let file = file.to_str().ok_or_else(|| anyhow!("Invalid page path: {}", file.display()))?;
+    run(dir, &["add", "--", file])?;
+    if run(dir, &["diff", "--cached", "--quiet", "--", file]).is_ok() {
+        // nothing changed
+        return Ok(());
+    }
+    run(dir, &["commit", "--message", message, "--", file])?;
+    run(dir, &["push"])?;
+    Ok(())
+}
+
+fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // fail instead of hanging on a credential prompt for private remotes
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git, is it installed?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        run(dir, &[&["-c", "user.name=tldrx", "-c", "user.email=tldrx@localhost"], args].concat())
+            .unwrap();
+    }
+
+    fn clone(remote: &Path, dir: &Path) {
+        git(remote, &["clone", "--quiet", remote.to_str().unwrap(), dir.to_str().unwrap()]);
+        git(dir, &["config", "user.name", "tldrx"]);
+        git(dir, &["config", "user.email", "tldrx@localhost"]);
+    }
+
+    fn write_page(dir: &Path, content: &str) -> std::path::PathBuf {
+        let file = dir.join("pages").join("common").join("tar.md");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, content).unwrap();
+        file
+    }
+
+    #[test]
+    fn pull_and_push_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let remote = tmp.path().join("remote.git");
+        let (alice, bob) = (tmp.path().join("alice"), tmp.path().join("bob"));
+        fs::create_dir(&remote).unwrap();
+        git(&remote, &["init", "--quiet", "--bare"]);
+        clone(&remote, &alice);
+        clone(&remote, &bob);
+        assert!(is_repo(&alice));
+
+        let file = write_page(&alice, "# tar\n");
+        commit_and_push(&alice, &file, "Add tar").unwrap();
+        // unchanged page would not be committed again
+        commit_and_push(&alice, &file, "Add tar again").unwrap();
+        pull(&bob).unwrap();
+        assert!(has_upstream(&bob));
+        assert_eq!(fs::read_to_string(bob.join("pages/common/tar.md")).unwrap(), "# tar\n");
+
+        let file = write_page(&alice, "# tar\n\n> alice\n");
+        commit_and_push(&alice, &file, "Update tar").unwrap();
+        let file = write_page(&bob, "# tar\n\n> bob\n");
+        git(&bob, &["commit", "--quiet", "--all", "--message", "Update tar"]);
+        let err = pull(&bob).unwrap_err().to_string();
+        assert!(err.contains("Conflicts"), "{}", err);
+        assert!(err.contains("pages/common/tar.md"), "{}", err);
+        assert!(err.contains("aborted"), "{}", err);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "# tar\n\n> bob\n");
+        assert_eq!(run(&bob, &["status", "--porcelain"]).unwrap(), "");
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::cache::{self, PageFile};
+use crate::config::Config;
+use crate::page::{Kind, Page};
+
+
+const INDEX_FILE: &str = "pages.index";
+const INDEX_VERSION: &str = "tldrx-index\t1";
+
+
+/// Prebuilt lookup table of every page under the official and private pages dirs.
+///
+/// Stored as a tab separated text file next to the official pages cache, a version line
+/// followed by `S <kind> <fingerprint> <root>` lines for sources then
+/// `P <source> <lang> <platform> <name> <description>` lines for pages.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Index {
+    pub sources: Vec<Source>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Source {
+    pub kind: Kind,
+    pub root: PathBuf,
+    fingerprint: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Entry {
+    /// Position in [`Index::sources`].
+    pub source: usize,
+    pub file: PageFile,
+    pub description: String,
+}
+
+impl Index {
+    pub fn build(config: &Config) -> Result<Self> {
+        let mut index = Index { sources: Vec::new(), entries: Vec::new() };
+        for (i, (root, kind)) in cache::get_sources(config)?.into_iter().enumerate() {
+            for file in cache::scan(&root) {
+                let page = Page::option_from(file.path(&root), kind.clone(), file.platform.clone(), config);
+                let description = match page.as_ref().map(Page::parse) {
+                    Some(Ok(doc)) => doc.description.into_iter().next().unwrap_or_default(),
+                    Some(Err(e)) => {
+                        // one broken page shouldn't cost the index of all others
+                        eprintln!("Skip page in index: {:#}", e);
+                        continue;
+                    }
+                    None => continue,
+                };
+                index.entries.push(Entry { source: i, file, description: clean(&description) });
+            }
+            index.sources.push(Source { fingerprint: fingerprint(&root), kind, root });
+        }
+        Ok(index)
+    }
+
+    /// Build and save the index for the current config.
+    pub fn rebuild(config: &Config) -> Result<()> {
+        let file = config.get_official_page_dir()?.join(INDEX_FILE);
+        Self::build(config)?.save(&file)
+    }
+
+    /// Load the index unless it is missing, broken or out of date.
+    pub fn load(config: &Config) -> Option<Self> {
+        let file = config.get_official_page_dir().ok()?.join(INDEX_FILE);
+        let index = Self::read(&file).ok()?;
+        let sources = cache::get_sources(config).ok()?;
+        let fresh = index.sources.len() == sources.len()
+            && index.sources.iter().zip(sources.iter()).all(|(s, (root, kind))| {
+                s.kind == *kind && s.root == *root && s.fingerprint == fingerprint(root)
+            });
+        fresh.then_some(index)
+    }
+
+    pub fn page_path(&self, entry: &Entry) -> PathBuf {
+        entry.file.path(&self.sources[entry.source].root)
+    }
+
+    fn save(&self, file: &Path) -> Result<()> {
+        let tmp = file.with_extension("tmp");
+        let mut out = BufWriter::new(
+            File::create(&tmp).with_context(|| format!("Fail to create index: {}", tmp.display()))?
+        );
+        writeln!(out, "{}", INDEX_VERSION)?;
+        for s in &self.sources {
+            let kind = match s.kind {
+                Kind::Official => String::new(),
+                Kind::Private(ref name) => clean(name),
+            };
+            writeln!(out, "S\t{}\t{}\t{}", kind, s.fingerprint, s.root.display())?;
+        }
+        for e in &self.entries {
+            writeln!(
+                out,
+                "P\t{}\t{}\t{}\t{}\t{}",
+                e.source,
+                e.file.lang,
+                e.file.platform,
+                e.file.name,
+                e.description
+            )?;
+        }
+        out.flush()?;
+        drop(out);
+        fs::rename(&tmp, file).with_context(|| format!("Fail to save index: {}", file.display()))
+    }
+
+    fn read(file: &Path) -> Result<Self> {
+        let mut lines = BufReader::new(File::open(file)?).lines();
+        let mut index = Index { sources: Vec::new(), entries: Vec::new() };
+        if lines.next().transpose()?.as_deref() != Some(INDEX_VERSION) {
+            return Err(anyhow!("Unknown index version: {}", file.display()));
+        }
+        for line in lines {
+            let line = line?;
+            let broken = || anyhow!("Broken index line: {}", line);
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("S") => {
+                    let (kind, fingerprint, root) = (
+                        fields.next().ok_or_else(broken)?,
+                        fields.next().ok_or_else(broken)?,
+                        fields.next().ok_or_else(broken)?,
+                    );
+                    index.sources.push(Source {
+                        kind: match kind {
+                            "" => Kind::Official,
+                            name => Kind::Private(name.to_string()),
+                        },
+                        fingerprint: fingerprint.parse()?,
+                        root: PathBuf::from(root),
+                    });
+                }
+                Some("P") => {
+                    let mut next = || fields.next().ok_or_else(broken);
+                    let source = next()?.parse()?;
+                    let file = PageFile {
+                        lang: next()?.to_string(),
+                        platform: next()?.parse()?,
+                        name: next()?.to_string(),
+                    };
+                    let description = next()?.to_string();
+                    index.entries.push(Entry { source, file, description });
+                }
+                _ => return Err(broken()),
+            }
+        }
+        Ok(index)
+    }
+}
+
+fn clean(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Adding or removing pages touches the mtime of the platform dirs, so hash them all.
+///
+/// Pages edited in place leave the dirs untouched, but `--edit` and `--update` rebuild the
+/// index anyway, so the pages themselves are not stated to keep lookups fast.
+fn fingerprint(root: &Path) -> u64 {
+    fn mtime(path: &Path) -> u128 {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+    }
+    fn sorted_dirs(dir: &Path) -> Vec<PathBuf> {
+        let mut dirs = fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect())
+            .unwrap_or_else(|_| Vec::new());
+        dirs.sort();
+        dirs
+    }
+
+    let mut hasher = DefaultHasher::new();
+    mtime(root).hash(&mut hasher);
+    for lang_dir in sorted_dirs(root) {
+        lang_dir.hash(&mut hasher);
+        mtime(&lang_dir).hash(&mut hasher);
+        for platform_dir in sorted_dirs(&lang_dir) {
+            platform_dir.hash(&mut hasher);
+            mtime(&platform_dir).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::PrivatePagesDir;
+
+    fn write_page(root: &Path, lang: &str, platform: &str, name: &str, description: &str) {
+        let dir = root.join(lang).join(platform);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.md", name)), format!("# {}\n\n> {}\n", name, description)).unwrap();
+    }
+
+    #[test]
+    fn build_save_and_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let official = tmp.path().join("cache");
+        let private = tmp.path().join("private");
+        write_page(&official.join(cache::PAGES_DIR), "pages", "common", "tar", "Archiving utility.");
+        write_page(&official.join(cache::PAGES_DIR), "pages.zh", "linux", "apt", "软件包\t管理。");
+        write_page(&private, "pages", "common", "deploy", "Deploy the project.");
+        let config = Config {
+            official_pages_dir: Some(official.clone()),
+            private_pages_dirs: vec![PrivatePagesDir {
+                name: "team".into(),
+                path: private.clone(),
+                priority: 0,
+                git_push: false,
+            }],
+            ..Default::default()
+        };
+
+        assert!(Index::load(&config).is_none());
+        Index::rebuild(&config).unwrap();
+        let index = Index::load(&config).unwrap();
+        assert_eq!(index, Index::build(&config).unwrap());
+        assert_eq!(index.sources[1].kind, Kind::Private("team".into()));
+
+        let mut names = index.entries.iter()
+            .map(|e| (e.file.name.as_str(), e.file.lang.as_str(), e.description.as_str()))
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec![
+            ("apt", "pages.zh", "软件包 管理。"),
+            ("deploy", "pages", "Deploy the project."),
+            ("tar", "pages", "Archiving utility."),
+        ]);
+        let deploy = index.entries.iter().find(|e| e.file.name == "deploy").unwrap();
+        assert_eq!(index.page_path(deploy), private.join("pages/common/deploy.md"));
+
+        // a new page makes the index stale
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        write_page(&private, "pages", "linux", "backup", "Backup the project.");
+        assert!(Index::load(&config).is_none());
+
+        // a page edited in place is picked up by the rebuild `--edit` runs
+        Index::rebuild(&config).unwrap();
+        write_page(&private, "pages", "common", "deploy", "Deploy the project to staging.");
+        Index::rebuild(&config).unwrap();
+        let index = Index::load(&config).unwrap();
+        let deploy = index.entries.iter().find(|e| e.file.name == "deploy").unwrap();
+        assert_eq!(deploy.description, "Deploy the project to staging.");
+    }
+
+    #[test]
+    fn skip_broken_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let private = tmp.path().join("private");
+        write_page(&private, "pages", "common", "deploy", "Deploy the project.");
+        write_page(&private, "pages", "common", "backup", "Backup the project.");
+        fs::write(private.join("pages/common/broken.md"), "").unwrap();
+        let config = Config {
+            official_pages_dir: Some(tmp.path().join("cache")),
+            private_pages_dirs: vec![PrivatePagesDir {
+                name: "team".into(),
+                path: private,
+                priority: 0,
+                git_push: false,
+            }],
+            ..Default::default()
+        };
+
+        let index = Index::build(&config).unwrap();
+        let mut names = index.entries.iter().map(|e| e.file.name.as_str()).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["backup", "deploy"]);
+    }
+}
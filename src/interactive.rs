@@ -0,0 +1,203 @@
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::page::{Example, Page, Segment};
+
+
+/// What to do with the filled command.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Print,
+    Copy,
+    Run,
+}
+
+/// Pick an example of the pages, fill its placeholders, then print, copy or run it.
+///
+/// Prompts go to stderr so the printed command could be captured from stdout.
+pub(crate) fn run(pages: &[Page]) -> Result<()> {
+    let mut examples = Vec::new();
+    for page in pages {
+        examples.extend(page.parse()?.examples.into_iter().filter(|e| !e.command.is_empty()));
+    }
+    if examples.is_empty() {
+        return Err(anyhow!("No examples to choose from"));
+    }
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut output = io::stderr();
+    let (command, action) = choose(&examples, &mut input, &mut output)?;
+    match action {
+        Action::Print => println!("{}", command),
+        Action::Copy => copy(&command)?,
+        Action::Run => {
+            let confirm = prompt(&mut input, &mut output, &format!("Run `{}`? [y/N]", command), "n")?;
+            if confirm.eq_ignore_ascii_case("y") || confirm.eq_ignore_ascii_case("yes") {
+                execute(&command)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn choose<R: BufRead, W: Write>(examples: &[Example], input: &mut R, output: &mut W) -> Result<(String, Action)> {
+    for (i, example) in examples.iter().enumerate() {
+        writeln!(output, "  [{}] {}", i + 1, example.description)?;
+        writeln!(output, "      {}", example.command_line())?;
+    }
+    writeln!(output)?;
+
+    let example = loop {
+        let choice = prompt(input, output, &format!("Select an example [1-{}]", examples.len()), "1")?;
+        match choice.parse::<usize>() {
+            Ok(n) if (1..=examples.len()).contains(&n) => break &examples[n - 1],
+            _ => writeln!(output, "Invalid choice: {}", choice)?,
+        }
+    };
+
+    let mut values = Vec::new();
+    for segment in &example.command {
+        if let Segment::Token(token) = segment {
+            values.push(prompt(input, output, token, token)?);
+        }
+    }
+    let command = fill(&example.command, &values);
+
+    writeln!(output, "\n  {}\n", command)?;
+    let action = loop {
+        match prompt(input, output, "[p]rint, [c]opy or [r]un", "p")?.as_str() {
+            "p" | "print" => break Action::Print,
+            "c" | "copy" => break Action::Copy,
+            "r" | "run" => break Action::Run,
+            other => writeln!(output, "Invalid choice: {}", other)?,
+        }
+    };
+    Ok((command, action))
+}
+
+/// Ask for a line of input, `default` is used when nothing is entered.
+fn prompt<R: BufRead, W: Write>(input: &mut R, output: &mut W, question: &str, default: &str) -> Result<String> {
+    write!(output, "{} ({}): ", question, default)?;
+    output.flush()?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Err(anyhow!("Input closed"));
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Replace the placeholders of `command` with `values` in order.
+fn fill(command: &[Segment<String>], values: &[String]) -> String {
+    let mut values = values.iter();
+    command.iter().map(|segment| match segment {
+        Segment::Normal(c) => c.as_str(),
+        Segment::Token(c) => values.next().unwrap_or(c).as_str(),
+    }).collect()
+}
+
+fn copy(command: &str) -> Result<()> {
+    let tools: &[&[&str]] = &[
+        &["pbcopy"],
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+        &["clip"],
+    ];
+    for tool in tools {
+        let child = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(command.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            eprintln!("Copied to clipboard");
+            return Ok(());
+        }
+    }
+    Err(anyhow!("No clipboard tool found, tried: pbcopy, wl-copy, xclip, xsel, clip"))
+}
+
+fn execute(command: &str) -> Result<()> {
+    let shell = env::var("SHELL").ok().filter(|s| !s.is_empty());
+    let mut cmd = match shell {
+        Some(ref s) => Command::new(s),
+        None if cfg!(windows) => Command::new("cmd"),
+        None => Command::new("sh"),
+    };
+    let flag = if shell.is_none() && cfg!(windows) { "/C" } else { "-c" };
+    let status = cmd
+        .arg(flag)
+        .arg(command)
+        .status()
+        .with_context(|| format!("Failed to run: {}", command))?;
+    if !status.success() {
+        return Err(anyhow!("Command exited with {}", status));
+    }
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::page::Segment::{Normal, Token};
+
+    fn examples() -> Vec<Example> {
+        vec![
+            Example {
+                description: "List files:".into(),
+                command: vec![Normal("tar tf ".into()), Token("source.tar".into())],
+            },
+            Example {
+                description: "Create an archive:".into(),
+                command: vec![
+                    Normal("tar cf ".into()),
+                    Token("target.tar".into()),
+                    Normal(" ".into()),
+                    Token("file".into()),
+                ],
+            },
+        ]
+    }
+
+    fn run_choose(input: &str) -> Result<(String, Action)> {
+        choose(&examples(), &mut Cursor::new(input), &mut Vec::new())
+    }
+
+    #[test]
+    fn test_fill() {
+        let command = &examples()[1].command;
+        assert_eq!(fill(command, &["a.tar".into(), "b".into()]), "tar cf a.tar b");
+        assert_eq!(fill(command, &["a.tar".into()]), "tar cf a.tar file");
+    }
+
+    #[test]
+    fn test_choose_defaults() {
+        assert_eq!(run_choose("\n\n\n").unwrap(), ("tar tf source.tar".into(), Action::Print));
+    }
+
+    #[test]
+    fn test_choose_filled() {
+        assert_eq!(
+            run_choose("3\n2\nbackup.tar\n\nx\nr\n").unwrap(),
+            ("tar cf backup.tar file".into(), Action::Run)
+        );
+        assert!(run_choose("2\n").is_err());
+    }
+}
@@ -5,8 +5,23 @@ use clap::Parser;
 
 mod cache;
 mod config;
+mod download;
+mod export;
+mod extract;
+mod git;
+mod index;
+mod interactive;
+mod lint;
+mod meta;
 mod page;
+mod pager;
 mod platform;
+mod search;
+mod store;
+mod style;
+mod suggest;
+mod verify;
+mod wrap;
 
 
 pub fn main() {
@@ -29,35 +44,106 @@ pub fn main() {
     };
 }
 
+#[allow(clippy::needless_borrow)]
 fn run(args: &config::Args, config: &config::Config) -> Result<()> {
     if args.update {
         return cache::update(config);
     }
+    if args.rollback {
+        return cache::rollback(config);
+    }
+    if args.cache_info {
+        return meta::info(config);
+    }
     if args.list {
         return cache::list(config);
     }
+    if let Some(ref query) = args.search {
+        return search::search(query, config);
+    }
+    if let Some(ref outdir) = args.export_html {
+        return export::export_html(outdir, args.source.as_deref(), config);
+    }
 
     let command = &args.command.join("-");
 
     if args.edit {
         return cache::edit(&command, args, config);
     }
+    if args.lint {
+        return lint::lint(command, config);
+    }
 
-    let pages = cache::seek(&command, config)?;
+    let pages = match args.render {
+        Some(ref file) => vec![page::Page::load(file, config)?],
+        None => {
+            meta::check_staleness(config);
+            cache::seek(&command, config)?
+        }
+    };
     if pages.is_empty() {
-        let bin = env!("CARGO_PKG_NAME");
-        let msg = format!(
-            "404: {}\n\n\
-             Try:\n  \
-               * {} -u\n  \
-               * {} -e {}\n  \
-               * https://github.com/tldr-pages/tldr/issues/new?title=page%20request:%20{}\
-            ", command, bin, bin, command, command
-        );
-        return Err(anyhow!(msg));
-    }
-    for page in pages {
-        page.render()?;
-    }
-    Ok(())
+        return Err(not_found(command, config));
+    }
+    if args.interactive {
+        return interactive::run(&pages);
+    }
+
+    let mut output = Vec::new();
+    if args.which {
+        for page in &pages {
+            page.render_which(&mut output)?;
+        }
+        return pager::show(&output, config);
+    }
+    if args.raw {
+        for page in &pages {
+            page.render_raw(&mut output)?;
+        }
+        return pager::show(&output, config);
+    }
+    match config.format {
+        config::Format::Json => page::Page::render_json(&pages, &mut output)?,
+        config::Format::Man => {
+            for page in pages {
+                page.render_man(&mut output)?;
+            }
+        }
+        config::Format::Text => {
+            for page in pages {
+                page.render(&mut output)?;
+            }
+        }
+    }
+    pager::show(&output, config)
+}
+
+fn not_found(command: &str, config: &config::Config) -> anyhow::Error {
+    let bin = env!("CARGO_PKG_NAME");
+    let mut msg = format!("404: {}\n", command);
+    match suggest::suggest(command, config) {
+        Ok(s) => {
+            for platform in &s.other_platforms {
+                msg.push_str(&format!("\n`{}` exists for {}; use -p {}", command, platform, platform));
+            }
+            for lang in &s.other_languages {
+                msg.push_str(&format!("\n`{}` exists in {}; use -L {}", command, lang, lang));
+            }
+            if !s.similar.is_empty() {
+                msg.push_str(&format!("\nDid you mean: {}?", s.similar.join(", ")));
+            }
+            if !s.other_platforms.is_empty() || !s.other_languages.is_empty() || !s.similar.is_empty() {
+                msg.push('\n');
+            }
+        }
+        Err(e) => eprintln!("Fail to look for similar pages: {:#}", e),
+    }
+    msg.push_str(&format!(
+        "\n\
+         Try:\n  \
+           * {} -u\n  \
+           * {} -e {}\n  \
+           * https://github.com/tldr-pages/tldr/issues/new?title=page%20request:%20{}\
+        ", bin, bin, command, command
+    ));
+    anyhow!(msg)
 }
@@ -0,0 +1,300 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use walkdir::WalkDir;
+
+use crate::cache;
+use crate::config::Config;
+use crate::page::{parse_code, Segment};
+
+
+const MAX_EXAMPLES: usize = 8;
+
+
+/// A problem found in a page, `line` starts from 1.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub line: usize,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.line, self.rule, self.message)
+    }
+}
+
+/// Lint pages of `target`, which could be a page file, a dir of pages or a command name.
+/// All private pages get linted without a target.
+pub(crate) fn lint(target: &str, config: &Config) -> Result<()> {
+    let files = if target.is_empty() {
+        config.private_pages_dirs.iter().flat_map(|d| find_pages(&d.path)).collect()
+    } else if Path::new(target).exists() {
+        find_pages(Path::new(target))
+    } else {
+        cache::seek(target, config)?.into_iter().map(|p| p.file).collect()
+    };
+    if files.is_empty() {
+        return Err(anyhow!("No pages found to lint: {}", target));
+    }
+
+    let mut problems = 0;
+    for file in &files {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to open: {}", file.display()))?;
+        for diagnostic in check(&content) {
+            println!("{}:{}", file.display(), diagnostic);
+            problems += 1;
+        }
+    }
+    match problems {
+        0 => Ok(()),
+        n => Err(anyhow!("{} problem(s) found in {} page(s)", n, files.len())),
+    }
+}
+
+fn find_pages(path: &Path) -> Vec<PathBuf> {
+    let mut files = WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.path().extension().unwrap_or_default() == "md")
+        .map(|e| e.into_path())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Check a page against the tldr-pages style guide, the classic syntax and the setext
+/// title one of the new syntax are both accepted.
+pub(crate) fn check(content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |line: usize, rule: &'static str, message: &str| {
+        diagnostics.push(Diagnostic { line, rule, message: message.to_string() })
+    };
+
+    let mut lines = content.split('\n').collect::<Vec<_>>();
+    if content.ends_with('\n') {
+        lines.pop();
+    } else if !content.is_empty() {
+        report(lines.len(), "newline-eof", "Page should end with a newline");
+    }
+
+    let setext = lines.get(1).is_some_and(|l| !l.is_empty() && l.chars().all(|c| c == '='));
+    let mut examples = 0;
+    let mut more_info = 0;
+    for (i, raw) in lines.iter().enumerate() {
+        let num = i + 1;
+        if raw.ends_with('\r') {
+            report(num, "line-ending", "Only Unix-style line endings allowed");
+        }
+        let line = raw.trim_end_matches('\r');
+        if line.ends_with([' ', '\t']) {
+            report(num, "trailing-whitespace", "Line should have no trailing whitespace");
+        }
+        if line.contains('\t') {
+            report(num, "tab", "Line should have no tabs");
+        }
+        if line.is_empty() {
+            if i > 0 && lines[i - 1].is_empty() {
+                report(num, "blank-lines", "Page should not contain consecutive blank lines");
+            }
+            continue;
+        }
+
+        if i == 0 {
+            check_title(line, setext, &mut report);
+            continue;
+        }
+        if setext && i == 1 {
+            continue;
+        }
+
+        match line.as_bytes()[0] {
+            b'>' => {
+                let text = match line.strip_prefix("> ") {
+                    Some(t) => t,
+                    None => {
+                        report(num, "description", "Description should start with `> `");
+                        line.trim_start_matches(['>', ' '])
+                    }
+                };
+                if text.starts_with("More information") {
+                    more_info += 1;
+                    check_more_info(num, text, &mut report);
+                } else if !text.starts_with(char::is_uppercase) && text.starts_with(char::is_alphabetic) {
+                    report(num, "description", "Description should start with a capital letter");
+                }
+                if !text.ends_with('.') {
+                    report(num, "description", "Description should end with a period");
+                }
+            }
+            b'-' => {
+                examples += 1;
+                let text = match line.strip_prefix("- ") {
+                    Some(t) => t,
+                    None => {
+                        report(num, "example", "Example description should start with `- `");
+                        line.trim_start_matches(['-', ' '])
+                    }
+                };
+                if text.starts_with(|c: char| c.is_alphabetic() && !c.is_uppercase()) {
+                    report(num, "example", "Example description should start with a capital letter");
+                }
+                if !text.ends_with(':') {
+                    report(num, "example", "Example description should end with a colon");
+                }
+            }
+            b'`' => {
+                if setext {
+                    report(num, "command", "Command should be indented with four spaces");
+                }
+                match line.strip_prefix('`').and_then(|l| l.strip_suffix('`')) {
+                    Some(code) if !code.is_empty() => check_command(num, code, &mut report),
+                    Some(_) => report(num, "command", "Command should not be empty"),
+                    None => report(num, "command", "Command should be wrapped in backticks"),
+                }
+            }
+            b' ' if setext => match line.strip_prefix("    ") {
+                Some(code) => check_command(num, code, &mut report),
+                None => report(num, "command", "Command should be indented with four spaces"),
+            },
+            b' ' => report(num, "command", "Command should be wrapped in backticks"),
+            b'#' => report(num, "title", "Page should contain only one title"),
+            _ => report(num, "syntax", "Line should start with one of `>`, `-` or `` ` ``"),
+        }
+    }
+
+    if lines.is_empty() {
+        report(1, "title", "Page should not be empty");
+    }
+    if more_info > 1 {
+        report(1, "more-info", "Page should contain only one information link");
+    }
+    if examples == 0 {
+        report(1, "example", "Page should contain at least one example");
+    } else if examples > MAX_EXAMPLES {
+        report(1, "example", &format!("Page should contain at most {} examples", MAX_EXAMPLES));
+    }
+    diagnostics.sort_by_key(|d| d.line);
+    diagnostics
+}
+
+fn check_title<F: FnMut(usize, &'static str, &str)>(line: &str, setext: bool, report: &mut F) {
+    let title = if setext {
+        line
+    } else {
+        match line.strip_prefix("# ") {
+            Some(t) => t,
+            None => {
+                report(1, "title", "Title should start with `# `");
+                return;
+            }
+        }
+    };
+    let valid = |c: char| c.is_alphanumeric() || " -_.+".contains(c);
+    if title.is_empty() || !title.chars().all(valid) {
+        report(1, "title", "Title should be the command name with alphanumeric, spaces, `-`, `_`, `.` or `+`");
+    }
+}
+
+fn check_more_info<F: FnMut(usize, &'static str, &str)>(num: usize, text: &str, report: &mut F) {
+    match text.strip_prefix("More information: ") {
+        Some(link) if link.starts_with('<') && link.ends_with(">.") => {}
+        Some(_) => report(num, "more-info", "Information link should be wrapped in angle brackets"),
+        None => report(num, "more-info", "Information link label should be exactly `More information: `"),
+    }
+}
+
+fn check_command<F: FnMut(usize, &'static str, &str)>(num: usize, code: &str, report: &mut F) {
+    if code.starts_with(char::is_whitespace) || code.ends_with(char::is_whitespace) {
+        report(num, "command", "Command should not begin or end with whitespace");
+    }
+    let mut balanced = true;
+    parse_code(code, |segment| {
+        if let Segment::Normal(c) = segment {
+            balanced &= !c.contains("{{") && !c.contains("}}");
+        }
+    });
+    if !balanced {
+        report(num, "placeholder", "Placeholders `{{` and `}}` should be balanced");
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(content: &str) -> Vec<(usize, &'static str)> {
+        check(content).into_iter().map(|d| (d.line, d.rule)).collect()
+    }
+
+    #[test]
+    fn check_valid_pages() {
+        let classic = "\
+# git commit
+
+> Commit files to the repository.
+> More information: <https://git-scm.com/docs/git-commit>.
+
+- Commit staged files to the repository with a message:
+
+`git commit --message \"{{message}}\"`
+";
+        assert_eq!(rules(classic), vec![]);
+
+        let setext = "\
+git commit
+==========
+
+> Commit files to the repository.
+
+- Commit staged files to the repository with a message:
+
+    git commit --message \"{{message}}\"
+";
+        assert_eq!(rules(setext), vec![]);
+    }
+
+    #[test]
+    fn check_invalid_page() {
+        let page = concat!(
+            "#git commit\n",
+            "\n",
+            "> commit files to the repository\n",
+            "> More information: https://git-scm.com/docs/git-commit.\n",
+            "\n",
+            "- Commit staged files to the repository with a message  \n",
+            "\n",
+            "\n",
+            "`git commit --message {{message}`\n",
+            "    git commit",
+        );
+        assert_eq!(rules(page), vec![
+            (1, "title"),
+            (3, "description"),
+            (3, "description"),
+            (4, "more-info"),
+            (6, "trailing-whitespace"),
+            (6, "example"),
+            (8, "blank-lines"),
+            (9, "placeholder"),
+            (10, "newline-eof"),
+            (10, "command"),
+        ]);
+    }
+
+    #[test]
+    fn check_example_count() {
+        let mut page = String::from("# tar\n\n> Archiving utility.\n");
+        assert_eq!(rules(&page), vec![(1, "example")]);
+        for _ in 0..9 {
+            page.push_str("\n- List files:\n\n`tar tf {{archive}}`\n");
+        }
+        assert_eq!(rules(&page), vec![(1, "example")]);
+    }
+}
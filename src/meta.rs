@@ -0,0 +1,299 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cache;
+use crate::config::{AutoUpdate, Config};
+use crate::git;
+use crate::store;
+
+
+const META_FILE: &str = "cache-meta.toml";
+/// When the last automatic update was started, in seconds since the Unix epoch.
+const ATTEMPT_FILE: &str = "update-attempt";
+const DAY: u64 = 24 * 60 * 60;
+/// Automatic updates are started at most once in this many seconds, so a failing one
+/// doesn't download or fork on every lookup.
+const AUTO_UPDATE_INTERVAL: u64 = 6 * 60 * 60;
+
+
+/// What the last `tldrx --update` fetched, stored next to the official pages cache.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CacheMeta {
+    /// Seconds since the Unix epoch.
+    pub updated_at: u64,
+    pub page_count: usize,
+    /// The archive of all languages, or one for each cached language.
+    #[serde(rename = "archive")]
+    pub archives: Vec<ArchiveMeta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ArchiveMeta {
+    /// `None` for the archive of all languages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub source_url: String,
+    pub sha256: String,
+    /// Validators of the archive, to download it again only if changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl CacheMeta {
+    pub fn new(archives: Vec<ArchiveMeta>, page_count: usize) -> Self {
+        Self { updated_at: now(), page_count, archives }
+    }
+
+    pub fn archive(&self, language: Option<&str>) -> Option<&ArchiveMeta> {
+        self.archives.iter().find(|a| a.language.as_deref() == language)
+    }
+
+    /// Mark the cache as up to date now.
+    pub fn touch(&mut self) {
+        self.updated_at = now();
+    }
+
+    /// Metadata of the official pages in `pages_dir`, `None` if they come without one.
+    pub fn load(pages_dir: &Path) -> Result<Option<Self>> {
+        let file = pages_dir.join(META_FILE);
+        if !file.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Fail to read cache metadata: {}", file.display()))?;
+        let meta = toml::from_str(&content)
+            .with_context(|| format!("Fail to parse cache metadata: {}", file.display()))?;
+        Ok(Some(meta))
+    }
+
+    pub fn save(&self, pages_dir: &Path) -> Result<()> {
+        let file = pages_dir.join(META_FILE);
+        fs::write(&file, toml::to_string(self)?)
+            .with_context(|| format!("Fail to save cache metadata: {}", file.display()))
+    }
+}
+
+/// When the official pages were updated, from the metadata or the mtime of the pages for
+/// caches updated by older versions. `None` if there is no cache at all.
+fn updated_at(config: &Config) -> Option<u64> {
+    let pages_dir = cache::official_pages_dir(config).ok()?;
+    if let Ok(Some(meta)) = CacheMeta::load(&pages_dir) {
+        return Some(meta.updated_at);
+    }
+    fs::metadata(pages_dir)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Warn about or refresh the official pages cache older than `cache_max_age_days`,
+/// following the `auto_update` policy. Failures only get reported, pages are shown anyway.
+pub(crate) fn check_staleness(config: &Config) {
+    let max_age = match config.get_cache_max_age() {
+        Some(age) => age,
+        None => return,
+    };
+    let age = match updated_at(config) {
+        Some(t) => Duration::from_secs(now().saturating_sub(t)),
+        None => return,
+    };
+    if age <= max_age {
+        return;
+    }
+
+    let bin = env!("CARGO_PKG_NAME");
+    match config.auto_update {
+        AutoUpdate::Off => {
+            eprintln!("Pages cache is {} old, run `{} -u` to update it", format_age(age), bin);
+        }
+        AutoUpdate::Foreground => {
+            if !claim_auto_update(config) {
+                return;
+            }
+            eprintln!("Pages cache is {} old, updating...", format_age(age));
+            if let Err(e) = cache::update(config) {
+                eprintln!("Fail to update pages cache: {:#}", e);
+            }
+        }
+        AutoUpdate::Background => {
+            if !claim_auto_update(config) {
+                return;
+            }
+            let spawned = env::current_exe().and_then(|exe| {
+                Command::new(exe)
+                    .arg("--update")
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+            });
+            if let Err(e) = spawned {
+                eprintln!("Fail to update pages cache in background: {}", e);
+            }
+        }
+    }
+}
+
+/// Record an automatic update attempt now, unless one was made within the interval or an
+/// update is running.
+fn claim_auto_update(config: &Config) -> bool {
+    let dir = match config.get_official_page_dir() {
+        Ok(d) => d,
+        Err(_) => return false,
+    };
+    let file = dir.join(ATTEMPT_FILE);
+    let last = fs::read_to_string(&file).ok().and_then(|s| s.trim().parse::<u64>().ok());
+    if last.is_some_and(|t| now().saturating_sub(t) < AUTO_UPDATE_INTERVAL) {
+        return false;
+    }
+    if store::lock(&dir).is_err() {
+        return false;
+    }
+    fs::write(&file, now().to_string()).is_ok()
+}
+
+/// Print the state of the official pages cache and the private pages dirs.
+pub(crate) fn info(config: &Config) -> Result<()> {
+    let pages_dir = cache::official_pages_dir(config)?;
+    println!("Official pages: {}", pages_dir.display());
+    match CacheMeta::load(&pages_dir)? {
+        Some(meta) => {
+            let age = Duration::from_secs(now().saturating_sub(meta.updated_at));
+            println!("  Updated:   {} ({} ago)", format_time(meta.updated_at), format_age(age));
+            for archive in &meta.archives {
+                if let Some(ref lang) = archive.language {
+                    println!("  Language:  {}", lang);
+                }
+                println!("  Source:    {}", archive.source_url);
+                println!("  SHA-256:   {}", archive.sha256);
+            }
+            println!("  Pages:     {}", meta.page_count);
+        }
+        None => match updated_at(config) {
+            Some(t) => println!("  Updated:   {} (no metadata, run `-u` to record)", format_time(t)),
+            None => println!("  Not cached yet, run `-u` to download"),
+        },
+    }
+    match config.get_cache_max_age() {
+        Some(age) => println!("  Max age:   {} days", age.as_secs() / DAY),
+        None => println!("  Max age:   unlimited"),
+    }
+    println!("  Auto update: {}", format!("{:?}", config.auto_update).to_lowercase());
+
+    if !config.private_pages_dirs.is_empty() {
+        println!("Private pages:");
+    }
+    for d in &config.private_pages_dirs {
+        let pages = cache::scan(&d.path).len();
+        println!(
+            "  {} (priority {}): {}, {} pages{}",
+            d.name,
+            d.priority,
+            d.path.display(),
+            pages,
+            if git::is_repo(&d.path) { ", git" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        s if s >= DAY => format!("{} day(s)", s / DAY),
+        s if s >= 60 * 60 => format!("{} hour(s)", s / 60 / 60),
+        s => format!("{} minute(s)", s / 60),
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`, days to date after http://howardhinnant.github.io/date_algorithms.html
+fn format_time(secs: u64) -> String {
+    let (days, rest) = ((secs / DAY) as i64, secs % DAY);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day, rest / 3600, rest / 60 % 60, rest % 60
+    )
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_time(1_670_025_600 + 3723), "2022-12-03 01:02:03 UTC");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(59)), "0 minute(s)");
+        assert_eq!(format_age(Duration::from_secs(2 * 60 * 60)), "2 hour(s)");
+        assert_eq!(format_age(Duration::from_secs(45 * DAY)), "45 day(s)");
+    }
+
+    #[test]
+    fn throttle_auto_update() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config { official_pages_dir: Some(tmp.path().to_owned()), ..Default::default() };
+        let dir = config.get_official_page_dir().unwrap();
+        assert!(claim_auto_update(&config));
+        assert!(!claim_auto_update(&config));
+
+        fs::write(dir.join(ATTEMPT_FILE), (now() - AUTO_UPDATE_INTERVAL).to_string()).unwrap();
+        let lock = store::lock(&dir).unwrap();
+        assert!(!claim_auto_update(&config));
+        drop(lock);
+        assert!(claim_auto_update(&config));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config { official_pages_dir: Some(tmp.path().to_owned()), ..Default::default() };
+        let pages_dir = cache::official_pages_dir(&config).unwrap();
+        assert_eq!(CacheMeta::load(&pages_dir).unwrap(), None);
+        assert_eq!(updated_at(&config), None);
+
+        fs::create_dir_all(&pages_dir).unwrap();
+        let archive = |language: Option<&str>| ArchiveMeta {
+            language: language.map(String::from),
+            source_url: "https://tldr.sh/assets/tldr.zip".into(),
+            sha256: "ab12".into(),
+            etag: Some("\"5f3a\"".into()),
+            last_modified: None,
+        };
+        let meta = CacheMeta::new(vec![archive(Some("en")), archive(Some("zh"))], 42);
+        meta.save(&pages_dir).unwrap();
+        let loaded = CacheMeta::load(&pages_dir).unwrap().unwrap();
+        assert_eq!(loaded, meta);
+        assert_eq!(loaded.archive(Some("zh")), Some(&archive(Some("zh"))));
+        assert_eq!(loaded.archive(None), None);
+        assert!(now() - updated_at(&config).unwrap() < 60);
+    }
+}
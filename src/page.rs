@@ -1,13 +1,23 @@
-use std::fs::File;
-use std::io::{BufReader, BufRead, Error, ErrorKind, stdin};
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, stdout, Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Result, Context};
-use crossterm::style::{Color, Stylize, Attribute, ContentStyle};
+use anyhow::{anyhow, Result, Context};
+use crossterm::style::ContentStyle;
 use crossterm::tty::IsTty;
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
+use url::Url;
 
 use crate::config::{Config, StyledChoice};
 use crate::platform::Platform;
+use crate::style;
+use crate::wrap;
+
+
+/// Source name of pages rendered from a local file.
+pub(crate) const LOCAL_SOURCE: &str = "local";
 
 
 #[derive(Debug)]
@@ -16,6 +26,8 @@ pub(crate) struct Page<'a> {
     pub kind: Kind,
     pub platform: Platform,
     pub config: &'a Config,
+    /// Markdown read ahead, instead of reading from `file`.
+    content: Option<String>,
 }
 
 impl<'a> Page<'a> {
@@ -29,184 +41,457 @@ impl<'a> Page<'a> {
             kind,
             platform,
             config,
+            content: None,
         })
     }
 
-    fn parse(&self) -> Result<Vec<Line>> {
-        let file = File::open(&self.file).with_context(||
-            format!("Failed to open: {}", self.file.display())
-        )?;
-        let mut line_iter = BufReader::new(file).lines().enumerate();
+    /// A page from any markdown file, or stdin if `file` is `-`, to preview it before
+    /// saving into a pages dir.
+    pub fn load(file: &Path, config: &'a Config) -> Result<Self> {
+        let content = if file == Path::new("-") {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content).context("Failed to read page from stdin")?;
+            content
+        } else {
+            fs::read_to_string(file).with_context(|| format!("Failed to open: {}", file.display()))?
+        };
+        Ok(Self {
+            file: file.to_owned(),
+            kind: Kind::Private(LOCAL_SOURCE.to_string()),
+            platform: config.platform.clone().unwrap_or(Platform::Common),
+            config,
+            content: Some(content),
+        })
+    }
 
-        // dorp the first line as its almost the same as what has inputed
-        let first_line = line_iter.next().map(|(_, l)| l).unwrap_or(
-            Err(Error::new(ErrorKind::Other, "First line must not blank!"))
-        ).with_context(||
-            format!("Failed to parse line [{}] for page: {}", 1, self.file.display())
-        )?;
+    /// Command name of the page, aka the file stem.
+    pub fn name(&self) -> &str {
+        self.file.file_stem().and_then(|s| s.to_str()).unwrap_or_default()
+    }
 
-        // v2 format: drop setext headding notation line ===
-        // https://github.com/tldr-pages/tldr/pull/958
-        if !first_line.starts_with('#') {
-            line_iter.next();
-        }
+    /// Language of the page, taken from its `pages.<lang>` folder.
+    pub fn language(&self) -> &str {
+        self.file
+            .parent()
+            .and_then(|p| p.parent())
+            .and_then(|p| p.file_name())
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.strip_prefix("pages."))
+            .unwrap_or("en")
+    }
 
-        // 64 line should cover most cases.
-        let mut lines: Vec<Line>= Vec::with_capacity(64);
-        while let Some((num, line)) = line_iter.next() {
-            let line = line.with_context(||
-                format!("Failed to parse line [{}] for page: {}", num, self.file.display())
-            )?;
-            lines.push(Line::from(&(line)))
+    /// Markdown of the page.
+    pub fn content(&self) -> Result<Cow<'_, str>> {
+        match self.content {
+            Some(ref c) => Ok(Cow::Borrowed(c)),
+            None => fs::read_to_string(&self.file).map(Cow::Owned).with_context(||
+                format!("Failed to open: {}", self.file.display())
+            ),
         }
-        Ok(lines)
     }
 
-    pub fn render(&self) -> Result<()> {
-        match self.config.styled {
-            StyledChoice::Auto if stdin().is_tty() => self.render_styled(),
-            StyledChoice::On => self.render_styled(),
-            _ => self.render_styless(),
+    pub fn parse(&self) -> Result<PageDoc> {
+        PageDoc::parse(&self.content()?).with_context(||
+            format!("Failed to parse page: {}", self.file.display())
+        )
+    }
+
+    pub fn render<W: Write>(&self, out: &mut W) -> Result<()> {
+        if is_styled(self.config) {
+            self.render_styled(out)
+        } else {
+            self.render_styless(out)
         }
     }
 
-    fn render_styled(&self) -> Result<()> {
-        // OPTIMIZE: maybe introduce a style customize feature and put it there
-        let normal_style = ContentStyle::new().with(Color::Green).attribute(Attribute::Bold);
-        let token_style = ContentStyle::new().with(Color::Cyan).attribute(Attribute::Bold);
-        let text_style = ContentStyle::new().with(Color::Blue).attribute(Attribute::Bold);
-        let blockquote_style = ContentStyle::new().with(Color::Grey);
-        let headding_style = ContentStyle::new().attribute(Attribute::Bold);
-        let meta_style = ContentStyle::new().attribute(Attribute::Bold).with(
-            match self.kind {
-                Kind::Official => Color::Green,
-                Kind::Private => Color::Red,
-            }
-        );
+    /// Print pages as a JSON array, one document per page.
+    pub fn render_json<W: Write>(pages: &[Page], out: &mut W) -> Result<()> {
+        let docs = pages.iter()
+            .map(|p| p.parse().map(|doc| (p, doc)))
+            .collect::<Result<Vec<_>>>()?;
+        let docs = docs.iter().map(|(p, doc)| PageJson::new(p, doc)).collect::<Vec<_>>();
+        writeln!(out, "{}", serde_json::to_string_pretty(&docs)?)?;
+        Ok(())
+    }
 
-        let column = 80 - 2;
-        let meta = format!("{:-^column$}", format!(" : {} :: {} : ", self.kind, self.platform));
-        println!("\n  {}", meta_style.apply(meta));
-        for line in self.parse()? {
-            match line {
-                Line::Blank => {
-                    println!()
-                },
-                Line::Code(s) => {
-                    print!("    ");
-                    parse_code(&s, |segment| match segment {
-                        Segment::Normal(c) => print!("{}", normal_style.apply(c)),
-                        Segment::Token(c) => print!("{}", token_style.apply(c)),
-                    });
-                    println!();
-                }
-                Line::Text(s) => {
-                    println!("  {}", text_style.apply(s))
-                }
-                Line::Headding(s) => {
-                    println!("  {}", headding_style.apply(s))
+    /// Print the page file verbatim.
+    pub fn render_raw<W: Write>(&self, out: &mut W) -> Result<()> {
+        out.write_all(self.content()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Print where the page comes from, tab separated for scripts.
+    pub fn render_which<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(out, "{}\t{}\t{}", self.kind, self.platform, self.file.display())?;
+        Ok(())
+    }
+
+    /// Print the page as a man page in roff.
+    pub fn render_man<W: Write>(&self, out: &mut W) -> Result<()> {
+        let doc = self.parse()?;
+        let source = format!("{} :: {}", self.kind, self.platform);
+        write_man(self.name(), &source, &doc, out)?;
+        Ok(())
+    }
+
+    fn render_styled<W: Write>(&self, out: &mut W) -> Result<()> {
+        let style = &self.config.style;
+        let meta_style = style.meta(&self.kind);
+        let width = wrap::width(self.config);
+
+        let doc = self.parse()?;
+        let column = width.unwrap_or(wrap::DEFAULT_WIDTH) - 2;
+        let meta = wrap::center(&format!(" : {} :: {} : ", self.kind, self.platform), '-', column);
+        writeln!(out, "\n  {}", meta_style.apply(meta))?;
+        writeln!(out)?;
+        print_text(&doc.title, 2, width, Some(style.heading), out)?;
+        writeln!(out)?;
+        for line in &doc.description {
+            print_text(line, 2, width, Some(style.description), out)?;
+        }
+        if let Some(ref url) = doc.more_info_text {
+            print_text(&format!("More information: <{}>.", url), 2, width, Some(style.blockquote), out)?;
+        }
+        for example in &doc.examples {
+            writeln!(out)?;
+            if !example.description.is_empty() {
+                print_text(&example.description, 2, width, Some(style.example), out)?;
+                writeln!(out)?;
+            }
+            let (first, rest) = code_columns(width);
+            for (i, line) in wrap::wrap_code(&example.command, first, rest).iter().enumerate() {
+                write!(out, "    ")?;
+                if i > 0 {
+                    write!(out, "{}", style.code.apply(wrap::CONTINUATION))?;
                 }
-                Line::Blockquote(s) => {
-                    println!("  {}", blockquote_style.apply(s))
+                for segment in line {
+                    match segment {
+                        Segment::Normal(c) => write!(out, "{}", style.code.apply(c))?,
+                        Segment::Token(c) => write!(out, "{}", style.token.apply(c))?,
+                    }
                 }
+                writeln!(out)?;
             }
         }
-        println!();
+        writeln!(out)?;
         Ok(())
     }
 
-    fn render_styless(&self) -> Result<()> {
-        println!("  : {} :: {} :", self.kind, self.platform);
-        for line in self.parse()? {
-            match line {
-                Line::Headding(s) => {
-                    println!("  {}", s);
-                }
-                Line::Blockquote(s) => {
-                    println!("  {}", s)
-                }
-                Line::Code(s) => {
-                    println!("    {}", s);
-                }
-                Line::Text(s) => {
-                    println!("  {}", s)
-                }
-                Line::Blank => println!(),
+    fn render_styless<W: Write>(&self, out: &mut W) -> Result<()> {
+        let width = wrap::width(self.config);
+        let doc = self.parse()?;
+        writeln!(out, "  : {} :: {} :", self.kind, self.platform)?;
+        writeln!(out)?;
+        print_text(&doc.title, 2, width, None, out)?;
+        writeln!(out)?;
+        for line in &doc.description {
+            print_text(line, 2, width, None, out)?;
+        }
+        if let Some(ref url) = doc.more_info_text {
+            print_text(&format!("More information: <{}>.", url), 2, width, None, out)?;
+        }
+        for example in &doc.examples {
+            writeln!(out)?;
+            if !example.description.is_empty() {
+                print_text(&example.description, 2, width, None, out)?;
+                writeln!(out)?;
+            }
+            let (first, rest) = code_columns(width);
+            for (i, line) in wrap::wrap_text(&example.command_line(), first, rest).iter().enumerate() {
+                let marker = if i > 0 { wrap::CONTINUATION } else { "" };
+                writeln!(out, "    {}{}", marker, line)?;
             }
         }
-        println!();
+        writeln!(out)?;
         Ok(())
     }
 }
 
 
-#[derive(Debug, Clone, Copy)]
+/// Print `text` word-wrapped at `indent`, the wrapped lines get a hanging indent.
+fn print_text<W: Write>(
+    text: &str,
+    indent: usize,
+    width: Option<usize>,
+    style: Option<ContentStyle>,
+    out: &mut W,
+) -> io::Result<()> {
+    let width = width.unwrap_or(usize::MAX);
+    let first = width.saturating_sub(indent);
+    let rest = first.saturating_sub(wrap::HANGING_INDENT);
+    for (i, line) in wrap::wrap_text(text, first, rest).iter().enumerate() {
+        let indent = if i > 0 { indent + wrap::HANGING_INDENT } else { indent };
+        match style {
+            Some(s) => writeln!(out, "{:indent$}{}", "", s.apply(line))?,
+            None => writeln!(out, "{:indent$}{}", "", line)?,
+        }
+    }
+    Ok(())
+}
+
+/// Columns for the first and wrapped lines of a command indented with four spaces.
+fn code_columns(width: Option<usize>) -> (usize, usize) {
+    let first = width.unwrap_or(usize::MAX).saturating_sub(4);
+    (first, first.saturating_sub(wrap::CONTINUATION.width()))
+}
+
+
+/// Roff with NAME, DESCRIPTION and EXAMPLES sections, placeholders are italic.
+fn write_man<W: Write>(name: &str, source: &str, doc: &PageDoc, out: &mut W) -> io::Result<()> {
+    let summary = doc.description.first().map(String::as_str).unwrap_or_default();
+    writeln!(
+        out,
+        ".TH \"{}\" 1 \"\" \"{} {}\" \"{}\"",
+        roff_escape(&name.to_uppercase()),
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        roff_escape(source)
+    )?;
+    writeln!(out, ".SH NAME")?;
+    writeln!(out, "{} \\- {}", roff_text(name), roff_escape(summary))?;
+
+    writeln!(out, ".SH DESCRIPTION")?;
+    for (i, line) in doc.description.iter().enumerate() {
+        if i > 0 {
+            writeln!(out, ".br")?;
+        }
+        writeln!(out, "{}", roff_text(line))?;
+    }
+    if let Some(ref url) = doc.more_info_text {
+        writeln!(out, ".PP")?;
+        writeln!(out, "More information:")?;
+        writeln!(out, ".UR {}", url)?;
+        writeln!(out, ".UE")?;
+    }
+
+    if !doc.examples.is_empty() {
+        writeln!(out, ".SH EXAMPLES")?;
+    }
+    for example in &doc.examples {
+        writeln!(out, ".PP")?;
+        if !example.description.is_empty() {
+            writeln!(out, "{}", roff_text(&example.description))?;
+        }
+        if example.command.is_empty() {
+            continue;
+        }
+        writeln!(out, ".RS 4")?;
+        write!(out, "\\&")?;
+        for segment in &example.command {
+            match segment {
+                Segment::Normal(c) => write!(out, "\\fB{}\\fR", roff_code(c))?,
+                Segment::Token(c) => write!(out, "\\fI{}\\fR", roff_code(c))?,
+            }
+        }
+        writeln!(out)?;
+        writeln!(out, ".RE")?;
+    }
+    Ok(())
+}
+
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('"', "\\(dq")
+}
+
+/// Escaped text line, which must not start as a request.
+fn roff_text(text: &str) -> String {
+    let text = roff_escape(text);
+    if text.starts_with(['.', '\'']) {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+/// Escaped code, hyphens become `\-` so they are copied as ASCII ones rather than dashes.
+fn roff_code(code: &str) -> String {
+    roff_escape(code).replace('-', "\\-")
+}
+
+
+pub(crate) fn is_styled(config: &Config) -> bool {
+    match config.styled {
+        StyledChoice::Auto => style::auto_styled(stdout().is_tty()),
+        StyledChoice::On => true,
+        StyledChoice::Off => false,
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Kind {
     Official,
-    Private,
+    /// Named after the private pages dir it comes from.
+    Private(String),
 }
 
 impl std::fmt::Display for Kind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let text = match self {
             Kind::Official => "official",
-            Kind::Private => "private",
+            Kind::Private(name) => name,
         };
         write!(f, "{}", text)
     }
 }
 
 
-#[derive(Debug)]
-pub(crate) enum Line {
-    Headding(String),
-    Blank,
-    Blockquote(String),
-    Text(String),
-    Code(String),
+/// Document model of a page, parsed from either the classic syntax or the setext title
+/// one of the [new syntax](https://github.com/tldr-pages/tldr/pull/958).
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct PageDoc {
+    pub title: String,
+    pub description: Vec<String>,
+    #[serde(skip)]
+    pub more_info: Option<Url>,
+    /// The more information link as written in the page, to be shown as is.
+    #[serde(rename = "more_info")]
+    pub more_info_text: Option<String>,
+    pub examples: Vec<Example>,
 }
 
-impl Line {
-    pub fn from(line: &str) -> Self {
-        match line.bytes().next() {
-            None => Self::Blank,
-            Some(b' ') => Self::Code(
-                line.trim_start_matches(char::is_whitespace).into()
-            ),
-            Some(b'`') => Self::Code({
-                // private pages might not strictly linted.
-                let line = line.trim_end();
-                line[1..(line.len() - 1)].into()
-            }),
-            Some(b'-') => Self::Text(
-                line.trim_start_matches(|c: char| c == '-' || c.is_whitespace()).into()
-            ),
-            Some(b'>') => Self::Blockquote(
-                line.trim_start_matches(|c: char| c == '>' || c.is_whitespace()).into()
-            ),
-            Some(b'#') => Self::Headding(
-                line.trim_start_matches(|c: char| c == '#' || c.is_whitespace()).into()
-            ),
-            Some(_) => Self::Text(
-                line.into()
-            ),
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct Example {
+    pub description: String,
+    /// Named `code` in JSON output, as it was before the document model.
+    #[serde(rename = "code")]
+    pub command: Vec<Segment<String>>,
+}
+
+impl Example {
+    /// The command as written in pages, with `{{token}}` placeholders.
+    pub fn command_line(&self) -> String {
+        self.command.iter().map(|segment| match segment {
+            Segment::Normal(c) => c.to_owned(),
+            Segment::Token(c) => format!("{{{{{}}}}}", c),
+        }).collect()
+    }
+}
+
+impl PageDoc {
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut lines = content.lines().peekable();
+        let title = match lines.next().map(str::trim_end) {
+            Some(l) if l.starts_with('#') => l.trim_start_matches(|c: char| c == '#' || c.is_whitespace()),
+            Some(l) if !l.is_empty() => {
+                // v2 format: drop setext headding notation line ===
+                if lines.peek().is_some_and(|l| l.starts_with('=')) {
+                    lines.next();
+                }
+                l
+            }
+            _ => return Err(anyhow!("First line must not blank!")),
+        };
+
+        let mut doc = PageDoc { title: title.to_string(), ..Default::default() };
+        for line in lines {
+            match line.bytes().next() {
+                None => {}
+                Some(b' ') | Some(b'\t') => doc.push_command(line.trim()),
+                Some(b'`') => doc.push_command({
+                    // private pages might not strictly linted.
+                    let line = line.trim_end();
+                    line.strip_prefix('`').map(|l| l.strip_suffix('`').unwrap_or(l)).unwrap_or(line)
+                }),
+                Some(b'-') => doc.examples.push(Example {
+                    description: line.trim_start_matches(|c: char| c == '-' || c.is_whitespace()).into(),
+                    command: Vec::new(),
+                }),
+                Some(b'>') | Some(b'#') => doc.push_text(
+                    line.trim_start_matches(|c: char| c == '>' || c == '#' || c.is_whitespace())
+                ),
+                Some(_) => doc.push_text(line),
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Text before any example describes the page, after that it describes an example.
+    fn push_text(&mut self, text: &str) {
+        let text = text.trim_end();
+        if !self.examples.is_empty() {
+            self.examples.push(Example { description: text.into(), command: Vec::new() });
+            return;
+        }
+        match more_info_link(text).and_then(|link| Url::parse(link).ok().map(|url| (link, url))) {
+            Some((link, url)) => {
+                self.more_info = Some(url);
+                self.more_info_text = Some(link.into());
+            }
+            None => self.description.push(text.into()),
+        }
+    }
+
+    fn push_command(&mut self, code: &str) {
+        let mut command = Vec::new();
+        parse_code(code, |segment| command.push(segment.into_owned()));
+        match self.examples.last_mut() {
+            Some(example) if example.command.is_empty() => example.command = command,
+            _ => self.examples.push(Example { description: String::new(), command }),
+        }
+    }
+}
+
+
+#[derive(Debug, Serialize)]
+struct PageJson<'a> {
+    name: &'a str,
+    platform: String,
+    language: &'a str,
+    kind: &'a str,
+    source: String,
+    #[serde(flatten)]
+    doc: &'a PageDoc,
+}
+
+impl<'a> PageJson<'a> {
+    fn new(page: &'a Page, doc: &'a PageDoc) -> Self {
+        Self {
+            name: page.name(),
+            platform: page.platform.to_string(),
+            language: page.language(),
+            kind: match page.kind {
+                Kind::Official => "official",
+                Kind::Private(_) => "private",
+            },
+            source: page.kind.to_string(),
+            doc,
         }
     }
 }
 
+/// The link in `More information: <https://...>.`
+fn more_info_link(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("More information:")?;
+    let start = rest.find('<')? + 1;
+    let end = rest[start..].find('>')? + start;
+    Some(&rest[start..end])
+}
+
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum Segment<'a> {
-    Normal(&'a str),
-    Token(&'a str),
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", content = "text", rename_all = "lowercase")]
+pub(crate) enum Segment<T> {
+    Normal(T),
+    Token(T),
 }
 
+impl Segment<&str> {
+    pub fn into_owned(self) -> Segment<String> {
+        match self {
+            Segment::Normal(c) => Segment::Normal(c.to_string()),
+            Segment::Token(c) => Segment::Token(c.to_string()),
+        }
+    }
+}
+
+#[allow(clippy::unused_unit)]
 pub(crate) fn parse_code<'a, F>(
     code: &'a str,
     mut consumer: F
 )
 where
-    F: FnMut(Segment<'a>) -> ()
+    F: FnMut(Segment<&'a str>) -> ()
 {
     let bytes = code.as_bytes();
     let (mut i, mut start, mut open, len) = (1, 0, false, bytes.len());
@@ -241,7 +526,7 @@ where
 mod test {
     use super::{*, Segment::*};
 
-    fn run_parse_code<'a>(code: &'a str) -> Vec<Segment<'a>> {
+    fn run_parse_code(code: &str) -> Vec<Segment<&str>> {
         let mut segments = Vec::new();
         parse_code(code, |s| segments.push(s));
         segments
@@ -308,6 +593,159 @@ mod test {
         );
     }
 
+    const CLASSIC_PAGE: &str = "\
+# tar
+
+> Archiving utility.
+> Often combined with a compression method.
+> More information: <https://www.gnu.org/software/tar>.
+
+- Create an archive from files:
+
+`tar cf {{target.tar}} {{file1 file2 ...}}`
+
+- List the contents of a tar file:
+
+`tar tvf {{source.tar}}`
+";
+
+    const SETEXT_PAGE: &str = "\
+tar
+===
+
+> Archiving utility.
+> Often combined with a compression method.
+> More information: <https://www.gnu.org/software/tar>.
+
+- Create an archive from files:
+
+    tar cf {{target.tar}} {{file1 file2 ...}}
+
+- List the contents of a tar file:
+
+    tar tvf {{source.tar}}
+";
+
+    fn tar_doc() -> PageDoc {
+        PageDoc {
+            title: "tar".into(),
+            description: vec![
+                "Archiving utility.".into(),
+                "Often combined with a compression method.".into(),
+            ],
+            more_info: Some(Url::parse("https://www.gnu.org/software/tar").unwrap()),
+            more_info_text: Some("https://www.gnu.org/software/tar".into()),
+            examples: vec![
+                Example {
+                    description: "Create an archive from files:".into(),
+                    command: vec![
+                        Normal("tar cf ".into()),
+                        Token("target.tar".into()),
+                        Normal(" ".into()),
+                        Token("file1 file2 ...".into()),
+                    ],
+                },
+                Example {
+                    description: "List the contents of a tar file:".into(),
+                    command: vec![Normal("tar tvf ".into()), Token("source.tar".into())],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_parse_doc() {
+        assert_eq!(PageDoc::parse(CLASSIC_PAGE).unwrap(), tar_doc());
+        assert_eq!(PageDoc::parse(SETEXT_PAGE).unwrap(), tar_doc());
+        assert!(PageDoc::parse("\n# tar\n").is_err());
+    }
+
+    #[test]
+    fn test_load_local_page() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("tar.md");
+        fs::write(&file, SETEXT_PAGE).unwrap();
+        let config = Config::default();
+        let page = Page::load(&file, &config).unwrap();
+        assert_eq!(page.name(), "tar");
+        assert_eq!(page.kind, Kind::Private(LOCAL_SOURCE.into()));
+        assert_eq!(page.platform, Platform::Common);
+        assert_eq!(page.parse().unwrap(), tar_doc());
+        assert!(Page::load(&tmp.path().join("missing.md"), &config).is_err());
+    }
+
+    #[test]
+    fn test_parse_doc_unlinted() {
+        let doc = PageDoc::parse("# tar  \n\nArchiving utility.\n\n- List:\n`tar tvf {{source.tar}}  \n").unwrap();
+        assert_eq!(doc.title, "tar");
+        assert_eq!(doc.description, vec!["Archiving utility."]);
+        assert_eq!(doc.examples[0].command_line(), "tar tvf {{source.tar}}");
+    }
+
+    #[test]
+    fn test_more_info_link() {
+        assert_eq!(
+            more_info_link("More information: <https://git-scm.com/docs/git-commit>."),
+            Some("https://git-scm.com/docs/git-commit")
+        );
+        assert_eq!(more_info_link("Commit files to the repository."), None);
+        assert_eq!(more_info_link("More information: https://git-scm.com."), None);
+    }
+
+    #[test]
+    fn test_write_man() {
+        let mut out = Vec::new();
+        write_man("tar", "official :: common", &tar_doc(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!(r#".TH "TAR" 1 "" "tldrx {}" "official :: common"
+.SH NAME
+tar \- Archiving utility.
+.SH DESCRIPTION
+Archiving utility.
+.br
+Often combined with a compression method.
+.PP
+More information:
+.UR https://www.gnu.org/software/tar
+.UE
+.SH EXAMPLES
+.PP
+Create an archive from files:
+.RS 4
+\&\fBtar cf \fR\fItarget.tar\fR\fB \fR\fIfile1 file2 ...\fR
+.RE
+.PP
+List the contents of a tar file:
+.RS 4
+\&\fBtar tvf \fR\fIsource.tar\fR
+.RE
+"#, env!("CARGO_PKG_VERSION")));
+
+        let mut out = Vec::new();
+        let doc = PageDoc::parse("# git\n\n> .git \"dir\".\n\n- Run:\n\n`git -C {{path\\to}}`\n").unwrap();
+        write_man("git", "team :: common", &doc, &mut out).unwrap();
+        let man = String::from_utf8(out).unwrap();
+        assert!(man.contains("\n\\&.git \\(dqdir\\(dq.\n"));
+        assert!(man.contains("\\fBgit \\-C \\fR\\fIpath\\eto\\fR"));
+    }
+
+    #[test]
+    fn test_doc_json() {
+        let doc = PageDoc::parse("# git\n\n> More information: <https://git-scm.com>.\n\n- Init:\n\n`git init`\n").unwrap();
+        assert_eq!(doc.more_info.as_ref().map(Url::as_str), Some("https://git-scm.com/"));
+        assert_eq!(
+            serde_json::to_string(&doc).unwrap(),
+            r#"{"title":"git","description":[],"more_info":"https://git-scm.com","examples":[{"description":"Init:","code":[{"type":"normal","text":"git init"}]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_segment_json() {
+        assert_eq!(
+            serde_json::to_string(&run_parse_code("git add {{file}}")).unwrap(),
+            r#"[{"type":"normal","text":"git add "},{"type":"token","text":"file"}]"#
+        );
+    }
+
     #[test]
     fn test_parse_code_i18n() {
         assert_eq!(
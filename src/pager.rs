@@ -0,0 +1,160 @@
+use std::env;
+use std::io::{self, stdout, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use crossterm::terminal;
+use crossterm::tty::IsTty;
+
+use crate::config::Config;
+
+
+const DEFAULT_PAGER: &str = "less";
+/// Keep colors, quit if one screen and leave the output on screen, as git does.
+const DEFAULT_LESS: &str = "FRX";
+
+
+/// Show `output` through the pager if stdout is a terminal and it is taller than the
+/// screen, or write it to stdout directly.
+pub(crate) fn show(output: &[u8], config: &Config) -> Result<()> {
+    let pager = match get_pager(config) {
+        Some(p) if stdout().is_tty() && is_taller_than_screen(output) => p,
+        _ => return ignore_broken_pipe(stdout().write_all(output)),
+    };
+
+    let mut child = shell(&pager)
+        .env("LESS", less_options(env::var("LESS").ok()))
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run pager: {}", pager))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // quitting the pager early closes the pipe
+        ignore_broken_pipe(stdin.write_all(output))?;
+    }
+    child.wait().with_context(|| format!("Failed to wait for pager: {}", pager))?;
+    Ok(())
+}
+
+/// The configured pager, then `$PAGER`, or `less`. An empty one disables paging.
+fn get_pager(config: &Config) -> Option<String> {
+    if config.no_pager {
+        return None;
+    }
+    let pager = match config.pager {
+        Some(ref p) => p.to_owned(),
+        None => env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string()),
+    };
+    Some(pager).filter(|p| !p.trim().is_empty())
+}
+
+/// `$LESS` with `-R` added if it would show colors as raw escapes, or the default.
+fn less_options(less: Option<String>) -> String {
+    match less {
+        None => DEFAULT_LESS.to_string(),
+        Some(less) if keeps_colors(&less) => less,
+        Some(less) => format!("{} -R", less).trim_start().to_string(),
+    }
+}
+
+/// Whether `-R`, `-r` or the long form of them is among the options.
+fn keeps_colors(less: &str) -> bool {
+    less.split_whitespace().any(|opt| match opt.strip_prefix("--") {
+        Some(long) => long.eq_ignore_ascii_case("raw-control-chars"),
+        None => opt.trim_start_matches('-').contains(['R', 'r']),
+    })
+}
+
+fn is_taller_than_screen(output: &[u8]) -> bool {
+    match terminal::size() {
+        Ok((_, rows)) => is_taller_than(output, rows),
+        Err(_) => false,
+    }
+}
+
+fn is_taller_than(output: &[u8], rows: u16) -> bool {
+    output.iter().filter(|&&b| b == b'\n').count() >= rows as usize
+}
+
+fn shell(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    cmd.arg(command);
+    cmd
+}
+
+fn ignore_broken_pipe(result: io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => Ok(r?),
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::sync::Mutex;
+
+    use lazy_static::lazy_static;
+
+    use super::*;
+
+    const PAGER: &str = "PAGER";
+
+    lazy_static! {
+        static ref MUTEX: Mutex<()> = Mutex::default();
+    }
+
+    fn clean_pager_env_run<F: FnOnce()>(f: F) {
+        let _lock = MUTEX.lock();
+        env::remove_var(PAGER);
+        f();
+    }
+
+    #[test]
+    fn test_get_pager() {
+        clean_pager_env_run(|| {
+            let mut config = Config::default();
+            assert_eq!(get_pager(&config).as_deref(), Some(DEFAULT_PAGER));
+            env::set_var(PAGER, "most");
+            assert_eq!(get_pager(&config).as_deref(), Some("most"));
+            env::set_var(PAGER, " ");
+            assert_eq!(get_pager(&config), None);
+
+            config.pager = Some("less -S".into());
+            assert_eq!(get_pager(&config).as_deref(), Some("less -S"));
+            config.pager = Some(String::new());
+            assert_eq!(get_pager(&config), None);
+            config.pager = Some("less -S".into());
+            config.no_pager = true;
+            assert_eq!(get_pager(&config), None);
+        });
+    }
+
+    #[test]
+    fn test_less_options() {
+        assert_eq!(less_options(None), DEFAULT_LESS);
+        assert_eq!(less_options(Some("FRX".into())), "FRX");
+        assert_eq!(less_options(Some("-S -r".into())), "-S -r");
+        assert_eq!(less_options(Some("--RAW-CONTROL-CHARS".into())), "--RAW-CONTROL-CHARS");
+        assert_eq!(less_options(Some("-S".into())), "-S -R");
+        assert_eq!(less_options(Some("--chop-long-lines".into())), "--chop-long-lines -R");
+        assert_eq!(less_options(Some(String::new())), "-R");
+    }
+
+    #[test]
+    fn test_is_taller_than() {
+        assert!(!is_taller_than(b"", 3));
+        assert!(!is_taller_than(b"a\nb\n", 3));
+        assert!(is_taller_than(b"a\nb\nc\n", 3));
+        assert!(is_taller_than(b"a\nb\nc\nd", 3));
+    }
+}
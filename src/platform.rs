@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Platform {
     Common,
     Linux,
@@ -30,6 +30,7 @@ impl std::str::FromStr for Platform {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.trim();
         let p = match s {
+            "common" => Platform::Common,
             "linux" => Platform::Linux,
             "osx" | "macos" => Platform::Osx,
             "windows" => Platform::Windows,
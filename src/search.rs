@@ -0,0 +1,299 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use anyhow::Result;
+use crossterm::style::{Attribute, ContentStyle, Stylize};
+
+use crate::cache;
+use crate::config::Config;
+use crate::page::{self, Example, Page, Segment};
+use crate::style::Style;
+
+
+const EXACT_NAME_SCORE: u32 = 32;
+const NAME_SCORE: u32 = 8;
+const DESCRIPTION_SCORE: u32 = 4;
+const EXAMPLE_SCORE: u32 = 2;
+const CODE_SCORE: u32 = 1;
+const MAX_SNIPPETS: usize = 3;
+
+
+struct Hit<'a> {
+    page: Page<'a>,
+    score: u32,
+    snippets: Vec<Snippet>,
+}
+
+enum Snippet {
+    Description(String),
+    Example(String),
+    Command(Example),
+}
+
+impl Snippet {
+    /// Text as displayed, placeholders of commands come without curly brackets.
+    fn text(&self) -> Cow<'_, str> {
+        match self {
+            Snippet::Description(s) | Snippet::Example(s) => Cow::Borrowed(s),
+            Snippet::Command(e) => e.command.iter().map(|segment| match segment {
+                Segment::Normal(c) | Segment::Token(c) => c.as_str(),
+            }).collect(),
+        }
+    }
+}
+
+pub(crate) fn search(query: &str, config: &Config) -> Result<()> {
+    let terms = query.split_whitespace().collect::<Vec<&str>>();
+    if terms.is_empty() {
+        return Ok(());
+    }
+
+    let mut hits = Vec::new();
+    for page in cache::walk(config)? {
+        if let Some(hit) = score(page, &terms)? {
+            hits.push(hit);
+        }
+    }
+    sort_hits(&mut hits);
+
+    let style = page::is_styled(config).then_some(&config.style);
+    for hit in hits {
+        print_hit(&hit, &terms, style);
+    }
+    Ok(())
+}
+
+/// Every term must match the page somewhere, or it is not a hit at all.
+fn score<'a>(page: Page<'a>, terms: &[&str]) -> Result<Option<Hit<'a>>> {
+    let name = page.name();
+    let mut matched = vec![false; terms.len()];
+    let mut score = 0;
+
+    if name.eq_ignore_ascii_case(&terms.join("-")) {
+        score += EXACT_NAME_SCORE;
+    }
+    for (i, term) in terms.iter().enumerate() {
+        if !find_matches(name, term).is_empty() {
+            matched[i] = true;
+            score += NAME_SCORE;
+        }
+    }
+
+    let doc = match page.parse() {
+        Ok(doc) => doc,
+        Err(e) => {
+            eprintln!("Skip page in search: {:#}", e);
+            return Ok(None);
+        }
+    };
+    let mut candidates = Vec::new();
+    candidates.extend(doc.description.into_iter().map(|d| (Snippet::Description(d), DESCRIPTION_SCORE)));
+    for example in doc.examples {
+        candidates.push((Snippet::Example(example.description.to_owned()), EXAMPLE_SCORE));
+        candidates.push((Snippet::Command(example), CODE_SCORE));
+    }
+
+    let mut snippets = Vec::new();
+    for (snippet, weight) in candidates {
+        let text = snippet.text();
+        let mut snippet_matched = false;
+        for (i, term) in terms.iter().enumerate() {
+            if !find_matches(&text, term).is_empty() {
+                matched[i] = true;
+                snippet_matched = true;
+                score += weight;
+            }
+        }
+        if snippet_matched && snippets.len() < MAX_SNIPPETS {
+            snippets.push(snippet);
+        }
+    }
+
+    if matched.contains(&false) {
+        return Ok(None);
+    }
+    Ok(Some(Hit { page, score, snippets }))
+}
+
+/// Best scored first, then by name.
+fn sort_hits(hits: &mut [Hit]) {
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.page.name().cmp(b.page.name())));
+}
+
+fn print_hit(hit: &Hit, terms: &[&str], style: Option<&Style>) {
+    let page = &hit.page;
+    let meta = format!("({} :: {})", page.kind, page.platform);
+    match style {
+        Some(s) => println!("  {}  {}", s.heading.apply(page.name()), s.meta(&page.kind).apply(meta)),
+        None => println!("  {}  {}", page.name(), meta),
+    }
+    for line in &hit.snippets {
+        match style {
+            Some(s) => print_styled_snippet(line, terms, s),
+            None => print_snippet(line),
+        }
+    }
+    println!();
+}
+
+fn print_snippet(snippet: &Snippet) {
+    match snippet {
+        Snippet::Description(s) | Snippet::Example(s) => println!("    {}", s),
+        Snippet::Command(e) => println!("      {}", e.command_line()),
+    }
+}
+
+fn print_styled_snippet(snippet: &Snippet, terms: &[&str], style: &Style) {
+    let text = snippet.text();
+    let mut ranges = terms.iter().flat_map(|t| find_matches(&text, t)).collect::<Vec<_>>();
+    ranges.sort_by_key(|r| r.start);
+    match snippet {
+        Snippet::Description(s) => {
+            print!("    ");
+            print_highlighted(s, 0, &ranges, style.description);
+        }
+        Snippet::Example(s) => {
+            print!("    ");
+            print_highlighted(s, 0, &ranges, style.example);
+        }
+        Snippet::Command(e) => {
+            print!("      ");
+            let mut offset = 0;
+            for segment in &e.command {
+                let (c, segment_style) = match segment {
+                    Segment::Normal(c) => (c, style.code),
+                    Segment::Token(c) => (c, style.token),
+                };
+                print_highlighted(c, offset, &ranges, segment_style);
+                offset += c.len();
+            }
+        }
+    }
+    println!();
+}
+
+/// Print `text` which starts at `offset` of the whole line, `ranges` are relative to the
+/// whole line and got underlined.
+fn print_highlighted(text: &str, offset: usize, ranges: &[Range<usize>], style: ContentStyle) {
+    let highlight = style.attribute(Attribute::Underlined);
+    let end = offset + text.len();
+    let mut pos = offset;
+    for range in ranges {
+        let (start, stop) = (range.start.max(pos), range.end.min(end));
+        if start >= stop {
+            continue;
+        }
+        if start > pos {
+            print!("{}", style.apply(&text[pos - offset..start - offset]));
+        }
+        print!("{}", highlight.apply(&text[start - offset..stop - offset]));
+        pos = stop;
+    }
+    if pos < end {
+        print!("{}", style.apply(&text[pos - offset..]));
+    }
+}
+
+/// Byte ranges of the case insensitive matches of `term` in `text`.
+fn find_matches(text: &str, term: &str) -> Vec<Range<usize>> {
+    let term = term.chars().flat_map(char::to_lowercase).collect::<Vec<char>>();
+    let mut ranges = Vec::new();
+    if term.is_empty() {
+        return ranges;
+    }
+
+    let mut from = 0;
+    for (start, _) in text.char_indices() {
+        if start < from {
+            continue;
+        }
+        let mut expected = term.iter();
+        let mut end = None;
+        for (i, c) in text[start..].char_indices() {
+            let all_equal = c.to_lowercase().all(|l| expected.next() == Some(&l));
+            if !all_equal {
+                break;
+            }
+            if expected.len() == 0 {
+                end = Some(start + i + c.len_utf8());
+                break;
+            }
+        }
+        if let Some(end) = end {
+            ranges.push(start..end);
+            from = end;
+        }
+    }
+    ranges
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::page::Kind;
+    use crate::platform::Platform;
+
+    #[test]
+    fn rank_name_description_and_example_hits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config::default();
+        let pages = [
+            ("tar", "# tar\n\n> Archive utility.\n\n- List files:\n\n`tar tvf {{file}}`\n"),
+            ("cp", "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{archive.tar}} {{path}}`\n"),
+            ("archive", "# archive\n\n> Store files.\n\n- Store:\n\n`store`\n"),
+            ("zip", "# zip\n\n> Compress files.\n\n- Create an archive:\n\n`zip {{out.zip}}`\n"),
+            ("archivemount", "# archivemount\n\n> Mount files.\n\n- Mount:\n\n`mount`\n"),
+            ("ls", "# ls\n\n> List files.\n\n- List:\n\n`ls`\n"),
+        ];
+        let mut hits = Vec::new();
+        for (name, content) in pages {
+            let file = tmp.path().join(format!("{}.md", name));
+            fs::write(&file, content).unwrap();
+            let page = Page::option_from(file, Kind::Official, Platform::Common, &config).unwrap();
+            hits.extend(score(page, &["archive"]).unwrap());
+        }
+        sort_hits(&mut hits);
+
+        let ranked = hits.iter().map(|h| (h.page.name(), h.score)).collect::<Vec<_>>();
+        assert_eq!(ranked, vec![
+            ("archive", EXACT_NAME_SCORE + NAME_SCORE),
+            ("archivemount", NAME_SCORE),
+            ("tar", DESCRIPTION_SCORE),
+            ("zip", EXAMPLE_SCORE),
+            ("cp", CODE_SCORE),
+        ]);
+        assert!(matches!(hits[2].snippets[..], [Snippet::Description(_)]));
+        assert!(score(
+            Page::option_from(tmp.path().join("tar.md"), Kind::Official, Platform::Common, &config).unwrap(),
+            &["archive", "gzip"],
+        ).unwrap().is_none());
+    }
+
+    #[test]
+    fn skip_broken_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config::default();
+        let file = tmp.path().join("archive.md");
+        fs::write(&file, "").unwrap();
+        let page = Page::option_from(file, Kind::Official, Platform::Common, &config).unwrap();
+        assert!(score(page, &["archive"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(find_matches("Extract an archive", "archive"), vec![11..18]);
+        assert_eq!(find_matches("TAR tar Tar", "tar"), vec![0..3, 4..7, 8..11]);
+        assert_eq!(find_matches("tar", "tarball"), vec![]);
+        assert_eq!(find_matches("aaa", "aa"), vec![0..2]);
+    }
+
+    #[test]
+    fn test_find_matches_i18n() {
+        assert_eq!(find_matches("从文件创建归档", "归档"), vec![15..21]);
+        assert_eq!(find_matches("ÄRGER", "ärger"), vec![0..6]);
+    }
+}
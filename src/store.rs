@@ -0,0 +1,328 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use fs2::FileExt;
+use walkdir::WalkDir;
+
+use crate::cache::{self, PAGES_DIR};
+
+
+const VERSIONS_DIR: &str = "versions";
+/// Names the current version then the previous one, a line for each.
+const POINTER_FILE: &str = "current";
+const LOCK_FILE: &str = "update.lock";
+/// Changes listed one by one up to this many, only counted beyond.
+const MAX_LISTED_CHANGES: usize = 20;
+
+
+#[derive(Debug, PartialEq)]
+struct Pointer {
+    current: String,
+    previous: Option<String>,
+}
+
+impl Pointer {
+    fn read(dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(dir.join(POINTER_FILE)).ok()?;
+        let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
+        let current = lines.next()?.to_string();
+        let previous = lines.next().map(str::to_string);
+        Some(Self { current, previous })
+    }
+
+    /// Written to a temp file then renamed, readers see either the old or the new one.
+    fn write(&self, dir: &Path) -> Result<()> {
+        let file = dir.join(POINTER_FILE);
+        let tmp = dir.join(format!("{}.tmp", POINTER_FILE));
+        let content = match self.previous {
+            Some(ref p) => format!("{}\n{}\n", self.current, p),
+            None => format!("{}\n", self.current),
+        };
+        fs::write(&tmp, content).with_context(|| format!("Fail to write: {}", tmp.display()))?;
+        fs::rename(&tmp, &file).with_context(|| format!("Fail to switch pages version: {}", file.display()))
+    }
+}
+
+/// Dir of the current official pages under the official pages `dir`.
+///
+/// Every update extracts to a new `versions/<version>` dir, then switches the pointer file
+/// to it. Caches of older versions come as a plain `tldr-pages` dir instead.
+pub(crate) fn pages_dir(dir: &Path) -> PathBuf {
+    match Pointer::read(dir) {
+        Some(p) => dir.join(VERSIONS_DIR).join(p.current),
+        None => dir.join(PAGES_DIR),
+    }
+}
+
+/// Held while updating the cache, released on drop even if the process crashes.
+pub(crate) struct Lock {
+    _file: File,
+}
+
+pub(crate) fn lock(dir: &Path) -> Result<Lock> {
+    fs::create_dir_all(dir).with_context(|| format!("Fail to create directory: {}", dir.display()))?;
+    let path = dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Fail to open lock file: {}", path.display()))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(Lock { _file: file }),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+            Err(anyhow!("Another update is running, locked: {}", path.display()))
+        }
+        Err(e) => Err(e).with_context(|| format!("Fail to lock: {}", path.display())),
+    }
+}
+
+/// A new empty version dir to extract pages into, not used until [`activate`]d.
+pub(crate) fn new_version(dir: &Path, _lock: &Lock) -> Result<(String, PathBuf)> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let name = format!("{}-{}", nanos, process::id());
+    let path = dir.join(VERSIONS_DIR).join(&name);
+    if path.exists() {
+        fs::remove_dir_all(&path).with_context(|| format!("Fail to clean up: {}", path.display()))?;
+    }
+    fs::create_dir_all(&path).with_context(|| format!("Fail to create directory: {}", path.display()))?;
+    Ok((name, path))
+}
+
+/// Switch to version `name` keeping the current one as the previous, then remove the
+/// others, including leftovers of interrupted updates.
+pub(crate) fn activate(dir: &Path, name: &str, _lock: &Lock) -> Result<()> {
+    let previous = match Pointer::read(dir) {
+        Some(p) => Some(p.current),
+        None => migrate_legacy(dir)?,
+    };
+    Pointer { current: name.to_string(), previous }.write(dir)?;
+    prune(dir)
+}
+
+/// Switch back to the previous version, the current one becomes the previous in turn.
+/// Returns the version switched to.
+pub(crate) fn rollback(dir: &Path, _lock: &Lock) -> Result<String> {
+    let pointer = Pointer::read(dir).ok_or_else(|| anyhow!("No versioned pages cache to roll back"))?;
+    let previous = pointer.previous
+        .filter(|p| dir.join(VERSIONS_DIR).join(p).is_dir())
+        .ok_or_else(|| anyhow!("No previous pages cache to roll back to"))?;
+    Pointer { current: previous.clone(), previous: Some(pointer.current) }.write(dir)?;
+    Ok(previous)
+}
+
+/// Move the plain `tldr-pages` dir of older versions into the versions, as the previous one.
+fn migrate_legacy(dir: &Path) -> Result<Option<String>> {
+    let legacy = dir.join(PAGES_DIR);
+    if !legacy.is_dir() {
+        return Ok(None);
+    }
+    let name = "legacy".to_string();
+    let path = dir.join(VERSIONS_DIR).join(&name);
+    if path.exists() {
+        fs::remove_dir_all(&path).with_context(|| format!("Fail to clean up: {}", path.display()))?;
+    }
+    fs::rename(&legacy, &path).with_context(|| format!("Fail to move: {}", legacy.display()))?;
+    Ok(Some(name))
+}
+
+/// Pages of a new version compared with the old one, as paths relative to the pages dir.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Changes {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Pages: {} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )?;
+        if self.added.len() + self.removed.len() + self.changed.len() > MAX_LISTED_CHANGES {
+            return Ok(());
+        }
+        let listed = [("+", &self.added), ("-", &self.removed), ("~", &self.changed)];
+        for (mark, paths) in listed {
+            for p in paths {
+                writeln!(f, "  {} {}", mark, p.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the pages in `old` with the ones in `new`, `old` may not exist.
+pub(crate) fn diff(old: &Path, new: &Path) -> Changes {
+    let relative = |root| cache::scan(root).iter().map(|p| p.path(Path::new(""))).collect::<Vec<_>>();
+    let (old_pages, new_pages) = (relative(old), relative(new));
+    let old_set = old_pages.iter().collect::<HashSet<_>>();
+    let new_set = new_pages.iter().collect::<HashSet<_>>();
+
+    let mut changes = Changes::default();
+    for p in &new_pages {
+        if !old_set.contains(p) {
+            changes.added.push(p.to_owned());
+        } else if !same_content(&old.join(p), &new.join(p)) {
+            changes.changed.push(p.to_owned());
+        }
+    }
+    changes.removed = old_pages.iter().filter(|p| !new_set.contains(p)).cloned().collect();
+    changes
+}
+
+/// Copy the files of `src` to `dst` as hard links where possible, nothing if `src` is missing.
+/// Versions never change files in place, so sharing them is safe.
+pub(crate) fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    for entry in WalkDir::new(src).into_iter().filter_map(|e| e.ok()) {
+        let relative = entry.path().strip_prefix(src)?;
+        let target = dst.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .with_context(|| format!("Fail to create directory: {}", target.display()))?;
+        } else if entry.file_type().is_file() && fs::hard_link(entry.path(), &target).is_err() {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Fail to copy: {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn same_content(a: &Path, b: &Path) -> bool {
+    let len = |p: &Path| fs::metadata(p).map(|m| m.len()).ok();
+    if len(a) != len(b) {
+        return false;
+    }
+    matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+fn prune(dir: &Path) -> Result<()> {
+    let pointer = match Pointer::read(dir) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let versions = dir.join(VERSIONS_DIR);
+    for entry in fs::read_dir(&versions).with_context(|| format!("Fail to read: {}", versions.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == pointer.current.as_str() || pointer.previous.as_deref().is_some_and(|p| name == p) {
+            continue;
+        }
+        let path = entry.path();
+        let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        removed.with_context(|| format!("Fail to clean up: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn add_version(dir: &Path, lock: &Lock, page: &str) -> String {
+        let (name, path) = new_version(dir, lock).unwrap();
+        fs::write(path.join(page), "").unwrap();
+        name
+    }
+
+    #[test]
+    fn activate_and_rollback() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join(PAGES_DIR)).unwrap();
+        fs::write(dir.join(PAGES_DIR).join("old"), "").unwrap();
+        assert_eq!(pages_dir(dir), dir.join(PAGES_DIR));
+
+        let lock = lock(dir).unwrap();
+        let first = add_version(dir, &lock, "first");
+        // nothing changes until activated
+        assert!(pages_dir(dir).join("old").is_file());
+        activate(dir, &first, &lock).unwrap();
+        assert!(pages_dir(dir).join("first").is_file());
+        assert!(!dir.join(PAGES_DIR).exists());
+
+        // a leftover of an interrupted update
+        fs::create_dir_all(dir.join(VERSIONS_DIR).join("0-1")).unwrap();
+        let second = add_version(dir, &lock, "second");
+        activate(dir, &second, &lock).unwrap();
+        assert_eq!(Pointer::read(dir), Some(Pointer { current: second.clone(), previous: Some(first.clone()) }));
+        let mut versions = fs::read_dir(dir.join(VERSIONS_DIR)).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        versions.sort();
+        let mut expected = vec![first.clone(), second.clone()];
+        expected.sort();
+        assert_eq!(versions, expected);
+
+        assert_eq!(rollback(dir, &lock).unwrap(), first);
+        assert!(pages_dir(dir).join("first").is_file());
+        assert_eq!(rollback(dir, &lock).unwrap(), second);
+        assert!(pages_dir(dir).join("second").is_file());
+    }
+
+    #[test]
+    fn rollback_without_previous() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lock = lock(tmp.path()).unwrap();
+        assert!(rollback(tmp.path(), &lock).is_err());
+        let first = add_version(tmp.path(), &lock, "first");
+        activate(tmp.path(), &first, &lock).unwrap();
+        assert!(rollback(tmp.path(), &lock).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (old, new) = (tmp.path().join("old"), tmp.path().join("new"));
+        let page = |root: &Path, path: &str, content: &str| {
+            let file = root.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        };
+        page(&old, "pages/common/tar.md", "# tar");
+        page(&old, "pages/common/zip.md", "# zip");
+        page(&old, "pages/linux/apt.md", "# apt");
+        page(&new, "pages/common/tar.md", "# tar");
+        page(&new, "pages/common/zip.md", "# zip\n");
+        page(&new, "pages.zh/linux/apt.md", "# apt");
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes, Changes {
+            added: vec![PathBuf::from("pages.zh/linux/apt.md")],
+            removed: vec![PathBuf::from("pages/linux/apt.md")],
+            changed: vec![PathBuf::from("pages/common/zip.md")],
+        });
+        assert_eq!(
+            changes.to_string(),
+            "Pages: 1 added, 1 removed, 1 changed\n  + pages.zh/linux/apt.md\n  - pages/linux/apt.md\n  ~ pages/common/zip.md\n"
+        );
+        assert_eq!(diff(&tmp.path().join("none"), &new).added.len(), 3);
+
+        let copied = tmp.path().join("copied");
+        copy_dir(&new.join("pages"), &copied.join("pages")).unwrap();
+        copy_dir(&tmp.path().join("none"), &copied.join("pages.ja")).unwrap();
+        let changes = diff(&new, &copied);
+        assert_eq!(changes.removed, vec![PathBuf::from("pages.zh/linux/apt.md")]);
+        assert!(changes.added.is_empty() && changes.changed.is_empty());
+    }
+
+    #[test]
+    fn exclusive_lock() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = lock(tmp.path()).unwrap();
+        assert!(lock(tmp.path()).is_err());
+        drop(first);
+        assert!(lock(tmp.path()).is_ok());
+    }
+}
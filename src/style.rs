@@ -1,11 +1,480 @@
+use std::convert::TryFrom;
+use std::env;
 
-#[derive(Debug)]
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle, Stylize};
+use serde::Deserialize;
+
+use crate::page::Kind;
+
+
+/// Styles of every element of the rendered pages.
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Style {
-    pub enable: bool,
+    /// Page titles, and page names in search results.
+    pub heading: ContentStyle,
+    pub description: ContentStyle,
+    pub example: ContentStyle,
+    pub code: ContentStyle,
+    pub token: ContentStyle,
+    pub blockquote: ContentStyle,
+    pub official: ContentStyle,
+    pub private: ContentStyle,
+}
+
+impl Style {
+    /// Style of the meta banner for the kind of pages.
+    pub fn meta(&self, kind: &Kind) -> ContentStyle {
+        match kind {
+            Kind::Official => self.official,
+            Kind::Private(_) => self.private,
+        }
+    }
+
+    fn with_overrides(mut self, raw: &RawStyle) -> Self {
+        let elements = [
+            (&mut self.heading, &raw.heading),
+            (&mut self.description, &raw.description),
+            (&mut self.example, &raw.example),
+            (&mut self.code, &raw.code),
+            (&mut self.token, &raw.token),
+            (&mut self.blockquote, &raw.blockquote),
+            (&mut self.official, &raw.official),
+            (&mut self.private, &raw.private),
+        ];
+        for (style, element) in elements {
+            if let Some(element) = element {
+                element.apply_to(style);
+            }
+        }
+        self
+    }
 }
 
 impl Style {
-    pub fn eprintln<T: Display>(&self, s: T) {
-        eprintln!();
+    /// Replace colors the terminal can not show with the nearest ones it can.
+    pub fn degrade(mut self, level: ColorLevel) -> Self {
+        for style in [
+            &mut self.heading,
+            &mut self.description,
+            &mut self.example,
+            &mut self.code,
+            &mut self.token,
+            &mut self.blockquote,
+            &mut self.official,
+            &mut self.private,
+        ] {
+            style.foreground_color = style.foreground_color.map(|c| degrade_color(c, level));
+            style.background_color = style.background_color.map(|c| degrade_color(c, level));
+        }
+        self
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Theme::default().into()
+    }
+}
+
+impl From<&RawStyle> for Style {
+    fn from(raw: &RawStyle) -> Self {
+        Style::from(raw.theme.unwrap_or_default()).with_overrides(raw)
+    }
+}
+
+
+/// Built-in color themes.
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Theme {
+    #[default]
+    Default,
+    /// Darker colors readable on light backgrounds.
+    Light,
+    /// No colors but attributes only.
+    Mono,
+}
+
+impl From<Theme> for Style {
+    fn from(theme: Theme) -> Self {
+        let plain = ContentStyle::new();
+        let bold = plain.attribute(Attribute::Bold);
+        match theme {
+            Theme::Default => Self {
+                heading: bold,
+                description: plain.with(Color::Grey),
+                example: bold.with(Color::Blue),
+                code: bold.with(Color::Green),
+                token: bold.with(Color::Cyan),
+                blockquote: plain.with(Color::Grey),
+                official: bold.with(Color::Green),
+                private: bold.with(Color::Red),
+            },
+            Theme::Light => Self {
+                heading: bold,
+                description: plain.with(Color::DarkGrey),
+                example: bold.with(Color::DarkBlue),
+                code: bold.with(Color::DarkGreen),
+                token: bold.with(Color::DarkMagenta),
+                blockquote: plain.with(Color::DarkGrey),
+                official: bold.with(Color::DarkGreen),
+                private: bold.with(Color::DarkRed),
+            },
+            Theme::Mono => Self {
+                heading: bold,
+                description: plain,
+                example: bold,
+                code: plain,
+                token: plain.attribute(Attribute::Underlined),
+                blockquote: plain.attribute(Attribute::Italic),
+                official: bold,
+                private: bold.attribute(Attribute::Reverse),
+            },
+        }
+    }
+}
+
+
+/// The `[style]` table of the config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RawStyle {
+    pub theme: Option<Theme>,
+    pub heading: Option<RawElement>,
+    pub description: Option<RawElement>,
+    pub example: Option<RawElement>,
+    pub code: Option<RawElement>,
+    pub token: Option<RawElement>,
+    pub blockquote: Option<RawElement>,
+    pub official: Option<RawElement>,
+    pub private: Option<RawElement>,
+}
+
+/// Overrides of the theme for an element, only the given fields are replaced.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RawElement {
+    pub fg: Option<RawColor>,
+    pub bg: Option<RawColor>,
+    pub attributes: Option<Vec<RawAttribute>>,
+}
+
+impl RawElement {
+    fn apply_to(&self, style: &mut ContentStyle) {
+        if let Some(ref fg) = self.fg {
+            style.foreground_color = fg.0;
+        }
+        if let Some(ref bg) = self.bg {
+            style.background_color = bg.0;
+        }
+        if let Some(ref attributes) = self.attributes {
+            style.attributes = Attributes::default();
+            attributes.iter().for_each(|a| style.attributes.set(a.0));
+        }
+    }
+}
+
+/// Color name like `dark_blue`, ANSI value `0`-`255`, `#rrggbb` or `reset` for the terminal
+/// default one.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct RawColor(Option<Color>);
+
+impl TryFrom<String> for RawColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid color: {}. Choice: [name | 0-255 | #rrggbb | reset]", s);
+        if s == "reset" {
+            return Ok(RawColor(None));
+        }
+        if let Ok(color) = Color::try_from(s.as_str()) {
+            return Ok(RawColor(Some(color)));
+        }
+        if let Ok(value) = s.parse::<u8>() {
+            return Ok(RawColor(Some(Color::AnsiValue(value))));
+        }
+        let hex = s.strip_prefix('#').filter(|h| h.len() == 6).ok_or_else(invalid)?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(RawColor(Some(Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? })))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct RawAttribute(Attribute);
+
+impl TryFrom<String> for RawAttribute {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let attribute = match s.as_str() {
+            "bold" => Attribute::Bold,
+            "dim" => Attribute::Dim,
+            "italic" => Attribute::Italic,
+            "underlined" => Attribute::Underlined,
+            "reverse" => Attribute::Reverse,
+            "crossed_out" => Attribute::CrossedOut,
+            _ => return Err(format!(
+                "Invalid attribute: {}. Choice: [bold | dim | italic | underlined | reverse | crossed_out]", s
+            )),
+        };
+        Ok(RawAttribute(attribute))
+    }
+}
+
+
+/// Should pages be styled when it is up to the environment, following the
+/// [NO_COLOR](https://no-color.org) and [CLICOLOR](https://bixense.com/clicolors/) conventions.
+pub(crate) fn auto_styled(is_tty: bool) -> bool {
+    if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    is_tty
+        && env::var_os("TERM").is_none_or(|t| t != "dumb")
+        && env::var_os("CLICOLOR").is_none_or(|c| c != "0")
+}
+
+
+/// Colors a terminal could show.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ColorLevel {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorLevel {
+    /// Guess from `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorLevel::TrueColor;
+        }
+        match env::var("TERM") {
+            Ok(term) if term.ends_with("-direct") => ColorLevel::TrueColor,
+            Ok(term) if term.contains("256color") => ColorLevel::Ansi256,
+            Ok(_) => ColorLevel::Ansi16,
+            // Windows terminals come without TERM
+            Err(_) if cfg!(windows) => ColorLevel::TrueColor,
+            Err(_) => ColorLevel::Ansi16,
+        }
+    }
+}
+
+/// The 16 basic colors in ANSI order with their xterm values.
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+/// Channel values of the 6x6x6 color cube of the 256 colors.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn degrade_color(color: Color, level: ColorLevel) -> Color {
+    match (level, color) {
+        (ColorLevel::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(rgb_to_ansi256((r, g, b))),
+        (ColorLevel::Ansi16, Color::Rgb { r, g, b }) => nearest_ansi16((r, g, b)),
+        (ColorLevel::Ansi16, Color::AnsiValue(n)) if n < 16 => ANSI_16[n as usize].0,
+        (ColorLevel::Ansi16, Color::AnsiValue(n)) => nearest_ansi16(ansi256_to_rgb(n)),
+        (_, c) => c,
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI_16.iter().min_by_key(|(_, value)| distance(rgb, *value)).map(|(c, _)| *c).unwrap_or(Color::Reset)
+}
+
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI_16[n as usize].1,
+        16..=231 => {
+            let i = (n - 16) as usize;
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+        }
+        _ => {
+            let v = 8 + 10 * (n - 232);
+            (v, v, v)
+        }
+    }
+}
+
+/// The nearest of the color cube and the grayscale ramp, the basic 16 ones vary by terminal.
+fn rgb_to_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs()).unwrap_or(0) as u8;
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, ansi256_to_rgb(gray)) < distance(rgb, ansi256_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load(content: &str) -> Result<Style, toml::de::Error> {
+        toml::from_str::<RawStyle>(content).map(|raw| Style::from(&raw))
+    }
+
+    #[test]
+    fn theme_and_overrides() {
+        assert_eq!(load("").unwrap(), Style::default());
+        assert_eq!(load(r#"theme = "light""#).unwrap(), Style::from(Theme::Light));
+
+        let style = load(r##"
+            theme = "mono"
+            code = { fg = "dark_blue" }
+            token = { fg = "#ff8000", bg = "236", attributes = ["bold", "italic"] }
+            official = { fg = "reset" }
+        "##).unwrap();
+        let mono = Style::from(Theme::Mono);
+        assert_eq!(style.code, mono.code.with(Color::DarkBlue));
+        assert_eq!(
+            style.token,
+            ContentStyle::new()
+                .with(Color::Rgb { r: 0xff, g: 0x80, b: 0 })
+                .on(Color::AnsiValue(236))
+                .attribute(Attribute::Bold)
+                .attribute(Attribute::Italic)
+        );
+        assert_eq!(style.official, mono.official);
+        assert_eq!(style.private, mono.private);
+    }
+
+    #[test]
+    fn invalid_style() {
+        assert!(load(r#"theme = "dark""#).is_err());
+        assert!(load(r##"code = { fg = "#ff80" }"##).is_err());
+        assert!(load(r#"code = { fg = "orange" }"#).is_err());
+        assert!(load(r#"code = { attributes = ["blink"] }"#).is_err());
+        assert!(load(r#"title = { fg = "red" }"#).is_err());
     }
-}
\ No newline at end of file
+
+    mod environment {
+        use std::env;
+        use std::sync::Mutex;
+
+        use lazy_static::lazy_static;
+
+        use super::super::*;
+
+        const VARS: [&str; 5] = ["NO_COLOR", "CLICOLOR", "CLICOLOR_FORCE", "TERM", "COLORTERM"];
+
+        lazy_static! {
+            static ref MUTEX: Mutex<()> = Mutex::default();
+        }
+
+        fn clean_color_env_run<F: FnOnce()>(vars: &[(&str, &str)], f: F) {
+            let _lock = MUTEX.lock();
+            for var in VARS {
+                env::remove_var(var);
+            }
+            for (k, v) in vars {
+                env::set_var(k, v);
+            }
+            f();
+        }
+
+        #[test]
+        fn tty_only() {
+            clean_color_env_run(&[], || {
+                assert!(auto_styled(true));
+                assert!(!auto_styled(false));
+            });
+            clean_color_env_run(&[("TERM", "xterm-256color")], || {
+                assert!(auto_styled(true));
+                assert!(!auto_styled(false));
+            });
+        }
+
+        #[test]
+        fn no_color() {
+            clean_color_env_run(&[("NO_COLOR", "1")], || assert!(!auto_styled(true)));
+            clean_color_env_run(&[("NO_COLOR", "")], || assert!(auto_styled(true)));
+            clean_color_env_run(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")], || {
+                assert!(!auto_styled(true))
+            });
+        }
+
+        #[test]
+        fn clicolor() {
+            clean_color_env_run(&[("CLICOLOR", "0")], || assert!(!auto_styled(true)));
+            clean_color_env_run(&[("CLICOLOR", "1")], || {
+                assert!(auto_styled(true));
+                assert!(!auto_styled(false));
+            });
+        }
+
+        #[test]
+        fn clicolor_force() {
+            clean_color_env_run(&[("CLICOLOR_FORCE", "1")], || assert!(auto_styled(false)));
+            clean_color_env_run(&[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0"), ("TERM", "dumb")], || {
+                assert!(auto_styled(false))
+            });
+            clean_color_env_run(&[("CLICOLOR_FORCE", "0")], || assert!(!auto_styled(false)));
+        }
+
+        #[test]
+        fn dumb_term() {
+            clean_color_env_run(&[("TERM", "dumb")], || assert!(!auto_styled(true)));
+        }
+
+        #[test]
+        fn color_level() {
+            clean_color_env_run(&[("TERM", "xterm")], || assert_eq!(ColorLevel::detect(), ColorLevel::Ansi16));
+            clean_color_env_run(&[("TERM", "xterm-256color")], || {
+                assert_eq!(ColorLevel::detect(), ColorLevel::Ansi256)
+            });
+            clean_color_env_run(&[("TERM", "xterm-direct")], || {
+                assert_eq!(ColorLevel::detect(), ColorLevel::TrueColor)
+            });
+            clean_color_env_run(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")], || {
+                assert_eq!(ColorLevel::detect(), ColorLevel::TrueColor)
+            });
+        }
+    }
+
+    #[test]
+    fn degrade_colors() {
+        let orange = Color::Rgb { r: 0xff, g: 0x80, b: 0 };
+        assert_eq!(degrade_color(orange, ColorLevel::TrueColor), orange);
+        assert_eq!(degrade_color(orange, ColorLevel::Ansi256), Color::AnsiValue(208));
+        assert_eq!(degrade_color(orange, ColorLevel::Ansi16), Color::DarkYellow);
+        assert_eq!(degrade_color(Color::Rgb { r: 30, g: 30, b: 30 }, ColorLevel::Ansi256), Color::AnsiValue(234));
+        assert_eq!(degrade_color(Color::AnsiValue(236), ColorLevel::Ansi256), Color::AnsiValue(236));
+        assert_eq!(degrade_color(Color::AnsiValue(4), ColorLevel::Ansi16), Color::DarkBlue);
+        assert_eq!(degrade_color(Color::AnsiValue(46), ColorLevel::Ansi16), Color::Green);
+        assert_eq!(degrade_color(Color::DarkBlue, ColorLevel::Ansi16), Color::DarkBlue);
+
+        let style = load(r##"token = { fg = "#ff8000", bg = "236" }"##).unwrap().degrade(ColorLevel::Ansi16);
+        assert_eq!(style.token.foreground_color, Some(Color::DarkYellow));
+        assert_eq!(style.token.background_color, Some(Color::Black));
+    }
+}
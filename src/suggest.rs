@@ -0,0 +1,106 @@
+use anyhow::Result;
+
+use crate::cache;
+use crate::config::Config;
+use crate::platform::Platform;
+
+
+const MAX_SUGGESTIONS: usize = 5;
+
+
+/// Hints for a command without pages.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Suggestions {
+    /// Closest page names, the closest comes first.
+    pub similar: Vec<String>,
+    /// Platforms not shown which have pages of the command.
+    pub other_platforms: Vec<Platform>,
+    /// Languages not shown which have pages of the command.
+    pub other_languages: Vec<String>,
+}
+
+/// Look for pages of every source, language and platform alike to `command`.
+pub(crate) fn suggest(command: &str, config: &Config) -> Result<Suggestions> {
+    let sources = cache::get_sources(config)?;
+    let (_, files) = cache::page_files(config, &sources);
+
+    let platform = config.platform.as_ref().unwrap_or(&Platform::Common);
+    let (mut other_platforms, mut other_languages) = (Vec::new(), Vec::new());
+    for (_, file) in files.iter().filter(|(_, f)| f.name == command) {
+        if file.platform == Platform::Common || file.platform == *platform {
+            let lang = file.lang.strip_prefix("pages.").unwrap_or("en");
+            other_languages.push(lang.to_string());
+        } else {
+            other_platforms.push(file.platform.clone());
+        }
+    }
+    other_platforms.sort_by_key(Platform::to_string);
+    other_platforms.dedup();
+    other_languages.sort();
+    other_languages.dedup();
+
+    let mut names = files.into_iter().map(|(_, f)| f.name).filter(|n| n != command).collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    Ok(Suggestions { similar: closest(command, names), other_platforms, other_languages })
+}
+
+/// Names within an edit distance of a third of `command` length, at least 1.
+fn closest(command: &str, names: Vec<String>) -> Vec<String> {
+    let max_distance = (command.chars().count() / 3).max(1);
+    let mut candidates = names.into_iter()
+        .map(|n| (distance(command, &n), n))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect::<Vec<_>>();
+    // stable sort, names are sorted already
+    candidates.sort_by_key(|(d, _)| *d);
+    candidates.into_iter().take(MAX_SUGGESTIONS).map(|(_, n)| n).collect()
+}
+
+/// Edit distance counting a transposition of adjacent chars as one edit, so `dokcer` is
+/// as close to `docker` as a single typo.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("docker", "docker"), 0);
+        assert_eq!(distance("dokcer", "docker"), 1);
+        assert_eq!(distance("dockr", "docker"), 1);
+        assert_eq!(distance("git-comit", "git-commit"), 1);
+        assert_eq!(distance("tar", "zip"), 3);
+        assert_eq!(distance("", "tar"), 3);
+        assert_eq!(distance("归档", "归档器"), 1);
+    }
+
+    #[test]
+    fn test_closest() {
+        let names = ["docker", "docker-compose", "dockerd", "doctl", "tar"].map(String::from).to_vec();
+        assert_eq!(closest("dokcer", names.clone()), vec!["docker", "dockerd"]);
+        assert_eq!(closest("ta", names.clone()), vec!["tar"]);
+        assert_eq!(closest("kubectl", names), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+
+use crate::download;
+use crate::config::Config;
+
+
+/// Published next to the archive in `sha256sum` format, or as a bare digest.
+const CHECKSUM_SUFFIX: &str = ".sha256";
+const SIGNATURE_SUFFIX: &str = ".minisig";
+
+
+/// Check the `archive` downloaded from `url` against the `pinned` or published SHA-256 and
+/// the signature of the configured public key. Returns the SHA-256 of the archive.
+pub(crate) fn verify(archive: &Path, url: &str, pinned: Option<&str>, config: &Config) -> Result<String> {
+    let actual = sha256(archive)?;
+
+    let expected = match pinned {
+        Some(sha) => Some(sha.to_owned()),
+        None => match fetch_sidecar(url, CHECKSUM_SUFFIX, config) {
+            Ok(content) => Some(parse_checksum(&content, file_name(url))?.to_string()),
+            Err(e) if config.require_checksum => {
+                return Err(e.context("Checksum is required but not available"));
+            }
+            Err(_) => None,
+        },
+    };
+    if let Some(expected) = expected {
+        if !expected.trim().eq_ignore_ascii_case(&actual) {
+            bail!("Checksum mismatch, expected {} but got {}", expected.trim(), actual);
+        }
+    }
+
+    if let Some(ref key) = config.archive_public_key {
+        let content = fetch_sidecar(url, SIGNATURE_SUFFIX, config).context("Signature is required but not available")?;
+        verify_signature(archive, key, &content)?;
+    }
+    Ok(actual)
+}
+
+fn sha256(file: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut f = File::open(file).with_context(|| format!("Could not open file: {}", file.display()))?;
+    io::copy(&mut f, &mut hasher).with_context(|| format!("Fail to hash: {}", file.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn fetch_sidecar(url: &str, suffix: &str, config: &Config) -> Result<String> {
+    let url = format!("{}{}", url, suffix);
+    let mut content = Vec::new();
+    download::fetch(&url, &mut content, config).with_context(|| format!("Fail to fetch: {}", url))?;
+    String::from_utf8(content).with_context(|| format!("Invalid content of: {}", url))
+}
+
+fn file_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+/// The digest of `name` in `sha256sum` output, or the only digest without a name.
+fn parse_checksum<'a>(content: &'a str, name: &str) -> Result<&'a str> {
+    let mut digests = content.lines().filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        let digest = parts.next()?;
+        let file = parts.next().map(|f| f.trim_start_matches('*'));
+        Some((digest, file))
+    });
+    let digest = match digests.clone().count() {
+        1 => digests.next().filter(|(_, f)| f.is_none_or(|f| file_name(f) == name)),
+        _ => digests.find(|(_, f)| f.is_some_and(|f| file_name(f) == name)),
+    };
+    match digest {
+        Some((d, _)) if d.len() == 64 && d.chars().all(|c| c.is_ascii_hexdigit()) => Ok(d),
+        Some((d, _)) => Err(anyhow!("Invalid SHA-256 checksum: {}", d)),
+        None => Err(anyhow!("No SHA-256 checksum of: {}", name)),
+    }
+}
+
+/// `key` is the base64 public key, or the content of a minisign public key file.
+fn verify_signature(archive: &Path, key: &str, signature: &str) -> Result<()> {
+    let key = if key.trim().contains('\n') { PublicKey::decode(key) } else { PublicKey::from_base64(key.trim()) }
+        .map_err(|e| anyhow!("Invalid public key: {}", e))?;
+    let signature = Signature::decode(signature).map_err(|e| anyhow!("Invalid signature: {}", e))?;
+    let content = fs::read(archive).with_context(|| format!("Could not open file: {}", archive.display()))?;
+    key.verify(&content, &signature, true)
+        .map_err(|e| anyhow!("Signature verification failed: {}", e))
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Signed "test", from the minisign-verify test suite.
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+    /// SHA-256 of "test".
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn archive(dir: &Path, content: &str) -> (std::path::PathBuf, String) {
+        let file = dir.join("tldr.zip");
+        fs::write(&file, content).unwrap();
+        let url = url::Url::from_file_path(&file).unwrap().to_string();
+        (file, url)
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        assert_eq!(parse_checksum(&format!("{}\n", SHA256), "tldr.zip").unwrap(), SHA256);
+        assert_eq!(parse_checksum(&format!("{}  tldr.zip", SHA256), "tldr.zip").unwrap(), SHA256);
+        let sums = format!("{}  tldr.zip\n{} *tldr-pages.en.zip", "0".repeat(64), SHA256);
+        assert_eq!(parse_checksum(&sums, "tldr-pages.en.zip").unwrap(), SHA256);
+        assert!(parse_checksum(&format!("{}  other.zip", SHA256), "tldr.zip").is_err());
+        assert!(parse_checksum("not-a-digest", "tldr.zip").is_err());
+    }
+
+    #[test]
+    fn checksum() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (file, url) = archive(tmp.path(), "test");
+        let mut config = Config::default();
+        // nothing published and nothing required
+        assert_eq!(verify(&file, &url, None, &config).unwrap(), SHA256);
+
+        config.require_checksum = true;
+        assert!(verify(&file, &url, None, &config).is_err());
+        fs::write(tmp.path().join("tldr.zip.sha256"), format!("{}  tldr.zip\n", SHA256)).unwrap();
+        assert_eq!(verify(&file, &url, None, &config).unwrap(), SHA256);
+
+        fs::write(&file, "tampered").unwrap();
+        assert!(verify(&file, &url, None, &config).is_err());
+        let pinned = SHA256.to_uppercase();
+        assert!(verify(&file, &url, Some(&pinned), &config).is_err());
+        fs::write(&file, "test").unwrap();
+        assert_eq!(verify(&file, &url, Some(&pinned), &config).unwrap(), SHA256);
+        fs::remove_file(tmp.path().join("tldr.zip.sha256")).unwrap();
+        assert_eq!(verify(&file, &url, Some(&pinned), &config).unwrap(), SHA256);
+    }
+
+    #[test]
+    fn signature() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (file, url) = archive(tmp.path(), "test");
+        let config = Config { archive_public_key: Some(PUBLIC_KEY.into()), ..Default::default() };
+        // missing signature
+        assert!(verify(&file, &url, None, &config).is_err());
+
+        fs::write(tmp.path().join("tldr.zip.minisig"), SIGNATURE).unwrap();
+        assert_eq!(verify(&file, &url, None, &config).unwrap(), SHA256);
+        let config = Config {
+            archive_public_key: Some(format!("untrusted comment: minisign public key\n{}\n", PUBLIC_KEY)),
+            ..Default::default()
+        };
+        assert!(verify(&file, &url, None, &config).is_ok());
+
+        fs::write(&file, "Test").unwrap();
+        assert!(verify(&file, &url, None, &config).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::stdout;
+use std::ops::Range;
+
+use crossterm::terminal;
+use crossterm::tty::IsTty;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::Config;
+use crate::page::Segment;
+
+
+/// Used when the terminal size is unknown, or the output is not a terminal.
+pub(crate) const DEFAULT_WIDTH: usize = 80;
+const MIN_WIDTH: usize = 20;
+/// Extra indent of the wrapped lines of a text.
+pub(crate) const HANGING_INDENT: usize = 2;
+/// Leads the wrapped lines of a command.
+pub(crate) const CONTINUATION: &str = "↪ ";
+
+
+/// Columns of the output, `None` if wrapping is disabled by `--width 0`.
+pub(crate) fn width(config: &Config) -> Option<usize> {
+    let width = match config.width {
+        Some(0) => return None,
+        Some(w) => w,
+        None if !stdout().is_tty() => DEFAULT_WIDTH,
+        None => terminal::size()
+            .ok()
+            .map(|(columns, _)| columns as usize)
+            .filter(|&c| c > 0)
+            .unwrap_or(DEFAULT_WIDTH),
+    };
+    Some(width.max(MIN_WIDTH))
+}
+
+/// Word-wrap `text` into lines of `first` columns for the first one and `rest` for the
+/// others. Words longer than a line are broken, so is CJK text between any characters.
+pub(crate) fn wrap_text(text: &str, first: usize, rest: usize) -> Vec<&str> {
+    breaks(text, first, rest).into_iter().map(|r| &text[r]).collect()
+}
+
+/// Like [`wrap_text`] but for the segments of a command, placeholders could be split into
+/// multiple lines.
+pub(crate) fn wrap_code(command: &[Segment<String>], first: usize, rest: usize) -> Vec<Vec<Segment<&str>>> {
+    let line = command.iter().map(|s| match s {
+        Segment::Normal(c) | Segment::Token(c) => c.as_str(),
+    }).collect::<String>();
+
+    breaks(&line, first, rest).into_iter().map(|range| {
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        for segment in command {
+            let (text, token) = match segment {
+                Segment::Normal(c) => (c, false),
+                Segment::Token(c) => (c, true),
+            };
+            let (start, end) = (range.start.max(offset), range.end.min(offset + text.len()));
+            if start < end {
+                let part = &text[start - offset..end - offset];
+                segments.push(if token { Segment::Token(part) } else { Segment::Normal(part) });
+            }
+            offset += text.len();
+        }
+        segments
+    }).collect()
+}
+
+/// Center `text` in `columns` by display width, padded with `fill`.
+pub(crate) fn center(text: &str, fill: char, columns: usize) -> String {
+    let pad = columns.saturating_sub(text.width());
+    let fill = fill.to_string();
+    format!("{}{}{}", fill.repeat(pad / 2), text, fill.repeat(pad - pad / 2))
+}
+
+/// Byte ranges of the wrapped lines, spaces around the breaks are dropped.
+fn breaks(text: &str, first: usize, rest: usize) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let (mut start, mut width) = (0, first.max(1));
+    loop {
+        let line = &text[start..];
+        if line.width() <= width {
+            lines.push(start..text.len());
+            return lines;
+        }
+
+        let (mut column, mut end, mut brk) = (0, start, None);
+        for (i, c) in line.char_indices() {
+            let w = c.width().unwrap_or(0);
+            if c == ' ' {
+                brk = Some(start + i);
+            }
+            if column + w > width {
+                break;
+            }
+            column += w;
+            end = start + i + c.len_utf8();
+            if w > 1 {
+                brk = Some(end);
+            }
+        }
+        let at = match brk {
+            Some(b) if b > start => b,
+            // not even a char fits, take it anyway to move on
+            _ if end == start => start + line.chars().next().map_or(1, char::len_utf8),
+            _ => end,
+        };
+        lines.push(start..start + text[start..at].trim_end().len());
+        start = at + text[at..].len() - text[at..].trim_start().len();
+        width = rest.max(1);
+        if start == text.len() {
+            return lines;
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::page::Segment::{Normal, Token};
+
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("Archiving utility.", 20, 20), vec!["Archiving utility."]);
+        assert_eq!(
+            wrap_text("Create an archive from files:", 12, 11),
+            vec!["Create an", "archive", "from files:"]
+        );
+        assert_eq!(wrap_text("abcdefghij klm", 4, 4), vec!["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(wrap_text("Archiving   utility.", 10, 10), vec!["Archiving", "utility."]);
+    }
+
+    #[test]
+    fn test_wrap_text_cjk() {
+        assert_eq!(wrap_text("从文件创建归档", 6, 6), vec!["从文件", "创建归", "档"]);
+        assert_eq!(wrap_text("列出 tar 文件的内容", 8, 8), vec!["列出 tar", "文件的内", "容"]);
+        assert_eq!(wrap_text("归档", 1, 1), vec!["归", "档"]);
+    }
+
+    #[test]
+    fn test_center() {
+        assert_eq!(center(" tar ", '-', 11), "--- tar ---");
+        assert_eq!(center(" tar ", '-', 10), "-- tar ---");
+        assert_eq!(center(" 团队 ", '-', 10), "-- 团队 --");
+        assert_eq!(center(" tar ", '-', 3), " tar ");
+    }
+
+    #[test]
+    fn test_wrap_code() {
+        let command = vec![
+            Normal("tar cf ".to_string()),
+            Token("target.tar".to_string()),
+            Normal(" ".to_string()),
+            Token("file1 file2".to_string()),
+        ];
+        assert_eq!(wrap_code(&command, 80, 80), vec![vec![
+            Normal("tar cf "), Token("target.tar"), Normal(" "), Token("file1 file2"),
+        ]]);
+        assert_eq!(wrap_code(&command, 20, 8), vec![
+            vec![Normal("tar cf "), Token("target.tar")],
+            vec![Token("file1")],
+            vec![Token("file2")],
+        ]);
+    }
+}